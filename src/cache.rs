@@ -0,0 +1,94 @@
+use crate::level::checksum;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Copies of files downloaded from repositories, kept so they can be
+/// revalidated instead of downloaded again and used while offline.
+#[derive(Debug, Clone)]
+pub struct Cache {
+    dir: String,
+}
+
+/// A downloaded file along with the validators the server sent for it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CacheEntry {
+    pub url: String,
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+    /// The signature the server sent with the file, if any.
+    #[serde(default)]
+    pub signature: Option<String>,
+    /// When the file was last downloaded or revalidated.
+    pub fetched_at: u64,
+    pub content: String,
+}
+
+impl CacheEntry {
+    pub fn new(
+        url: &str,
+        etag: Option<String>,
+        last_modified: Option<String>,
+        content: String,
+    ) -> CacheEntry {
+        CacheEntry {
+            url: url.to_string(),
+            etag,
+            last_modified,
+            signature: None,
+            fetched_at: now(),
+            content,
+        }
+    }
+
+    /// Marks the entry as just revalidated.
+    pub fn refreshed(self) -> CacheEntry {
+        CacheEntry {
+            fetched_at: now(),
+            ..self
+        }
+    }
+
+    /// How many seconds ago the entry was downloaded or revalidated.
+    pub fn age(&self) -> u64 {
+        now().saturating_sub(self.fetched_at)
+    }
+}
+
+impl Cache {
+    /// A cache kept in the `repositories` directory of `cache_dir`.
+    pub fn new(cache_dir: &str) -> Cache {
+        Cache {
+            dir: cache_dir.to_string() + "/repositories",
+        }
+    }
+
+    fn file(&self, url: &str) -> String {
+        format!("{}/{}.json", self.dir, &checksum(url)[0..32])
+    }
+
+    /// The cached copy of `url`, if there is one that can be read.
+    pub fn get(&self, url: &str) -> Option<CacheEntry> {
+        let content = fs::read_to_string(self.file(url)).ok()?;
+        let entry: CacheEntry = serde_json::from_str(&content).ok()?;
+        if entry.url == url {
+            Some(entry)
+        } else {
+            None
+        }
+    }
+
+    /// Stores `entry`, replacing any earlier copy of the same URL.
+    pub fn put(&self, entry: &CacheEntry) -> Result<(), String> {
+        fs::create_dir_all(&self.dir).map_err(|e| e.to_string())?;
+        let content = serde_json::to_string(entry).map_err(|e| e.to_string())?;
+        fs::write(self.file(&entry.url), content).map_err(|e| e.to_string())
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
@@ -1,5 +1,11 @@
-use crossterm::event::{read, Event, KeyCode, KeyEventKind};
+use crossterm::event::{
+    read, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers, MouseButton, MouseEventKind,
+};
+use std::{collections::BTreeMap, fmt, fs, path, sync::OnceLock};
 
+static KEYMAP: OnceLock<Keymap> = OnceLock::new();
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Control {
     Up,
     Down,
@@ -8,32 +14,417 @@ pub enum Control {
     Help,
     Quit,
     Action,
+    Pull,
     Select,
     GotoTop,
     GotoBottom,
+    New,
+    Edit,
+    Delete,
+    MoveUp,
+    MoveDown,
+    Download,
+    Search,
+    /// A left click at the given `(column, row)` of the terminal.
+    Click(u16, u16),
+    ScrollUp,
+    ScrollDown,
     None,
 }
 
 impl Control {
+    /// Every control that can be bound to a key, in the order they are
+    /// listed in the keys file and the help menu.
+    pub const BINDABLE: [Control; 18] = [
+        Control::Up,
+        Control::Down,
+        Control::Left,
+        Control::Right,
+        Control::Action,
+        Control::Pull,
+        Control::Select,
+        Control::Help,
+        Control::Quit,
+        Control::GotoTop,
+        Control::GotoBottom,
+        Control::New,
+        Control::Edit,
+        Control::Delete,
+        Control::MoveUp,
+        Control::MoveDown,
+        Control::Download,
+        Control::Search,
+    ];
+
+    /// The name used for this control in the keys file.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Control::Up => "up",
+            Control::Down => "down",
+            Control::Left => "left",
+            Control::Right => "right",
+            Control::Action => "action",
+            Control::Pull => "pull",
+            Control::Select => "select",
+            Control::Help => "help",
+            Control::Quit => "quit",
+            Control::GotoTop => "goto_top",
+            Control::GotoBottom => "goto_bottom",
+            Control::New => "new",
+            Control::Edit => "edit",
+            Control::Delete => "delete",
+            Control::MoveUp => "move_up",
+            Control::MoveDown => "move_down",
+            Control::Download => "download",
+            Control::Search => "search",
+            Control::Click(_, _) => "click",
+            Control::ScrollUp => "scroll_up",
+            Control::ScrollDown => "scroll_down",
+            Control::None => "none",
+        }
+    }
+
+    /// A short description of what the control does, shown in the help menu.
+    pub fn description(&self) -> &'static str {
+        match self {
+            Control::Up => "Move Up",
+            Control::Down => "Move Down",
+            Control::Left => "Move Left",
+            Control::Right => "Move Right",
+            Control::Action => "Toggle surrounding blocks (if able)",
+            Control::Pull => "Pull the block you're facing",
+            Control::Select => "Confirm a menu selection",
+            Control::Help => "Show this help menu",
+            Control::Quit => "Quit",
+            Control::GotoTop => "Scroll to the top of a menu",
+            Control::GotoBottom => "Scroll to the bottom of a menu",
+            Control::New => "Add a new item to a menu",
+            Control::Edit => "Edit the selected item in a menu",
+            Control::Delete => "Delete the selected item in a menu",
+            Control::MoveUp => "Move the selected item up in a menu",
+            Control::MoveDown => "Move the selected item down in a menu",
+            Control::Download => "Download the selected repository for offline play",
+            Control::Search => "Search the levels in a repository",
+            Control::Click(_, _) | Control::ScrollUp | Control::ScrollDown | Control::None => "",
+        }
+    }
+
+    fn default_keys(&self) -> &'static [&'static str] {
+        match self {
+            Control::Up => &["Up", "w", "k"],
+            Control::Down => &["Down", "s", "j"],
+            Control::Left => &["Left", "a", "h"],
+            Control::Right => &["Right", "d", "l"],
+            Control::Action => &["Space"],
+            Control::Pull => &["p"],
+            Control::Select => &["Enter"],
+            Control::Help => &["H"],
+            Control::Quit => &["q"],
+            Control::GotoTop => &["g"],
+            Control::GotoBottom => &["G"],
+            Control::New => &["n"],
+            Control::Edit => &["e"],
+            Control::Delete => &["x"],
+            Control::MoveUp => &["K"],
+            Control::MoveDown => &["J"],
+            Control::Download => &["o"],
+            Control::Search => &["/"],
+            _ => &[],
+        }
+    }
+
     pub fn read_input() -> Self {
-        if let Ok(Event::Key(event)) = read() {
-            if event.kind == KeyEventKind::Release {
-                return Self::None;
-            }
-            return match event.code {
-                KeyCode::Up | KeyCode::Char('w') | KeyCode::Char('k') => Self::Up,
-                KeyCode::Down | KeyCode::Char('s') | KeyCode::Char('j') => Self::Down,
-                KeyCode::Left | KeyCode::Char('a') | KeyCode::Char('h') => Self::Left,
-                KeyCode::Right | KeyCode::Char('d') | KeyCode::Char('l') => Self::Right,
-                KeyCode::Char('g') => Self::GotoTop,
-                KeyCode::Char('G') => Self::GotoBottom,
-                KeyCode::Char(' ') => Self::Action,
-                KeyCode::Char('H') => Self::Help,
-                KeyCode::Char('q') => Self::Quit,
-                KeyCode::Enter => Self::Select,
+        loop {
+            return match read() {
+                Ok(Event::Key(event)) => {
+                    if event.kind == KeyEventKind::Release {
+                        return Self::None;
+                    }
+                    Keymap::active().control(&KeyBinding::from(event))
+                }
+                // Mouse movement is reported constantly while mouse capture is
+                // enabled, so only clicks and scrolling are passed on to avoid
+                // redrawing on every move.
+                Ok(Event::Mouse(event)) => match event.kind {
+                    MouseEventKind::Down(MouseButton::Left) => Self::Click(event.column, event.row),
+                    MouseEventKind::ScrollUp => Self::ScrollUp,
+                    MouseEventKind::ScrollDown => Self::ScrollDown,
+                    _ => continue,
+                },
+                _ => Self::None,
+            };
+        }
+    }
+}
+
+/// A key pressed while typing text. Read without going through the
+/// `Keymap` so that any character can be typed.
+pub enum TextInput {
+    Char(char),
+    Backspace,
+    Submit,
+    Cancel,
+    None,
+}
+
+impl TextInput {
+    pub fn read_input() -> Self {
+        match read() {
+            Ok(Event::Key(event)) if event.kind != KeyEventKind::Release => match event.code {
+                KeyCode::Enter => Self::Submit,
+                KeyCode::Esc => Self::Cancel,
+                KeyCode::Backspace => Self::Backspace,
+                KeyCode::Char(c) if !event.modifiers.contains(KeyModifiers::CONTROL) => {
+                    Self::Char(c)
+                }
                 _ => Self::None,
+            },
+            _ => Self::None,
+        }
+    }
+}
+
+/// A single key, with any modifiers, that can be bound to a `Control`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyBinding {
+    pub code: KeyCode,
+    pub modifiers: KeyModifiers,
+}
+
+impl KeyBinding {
+    /// Parses a key such as `q`, `Space`, `Shift+Up` or `Ctrl+c`.
+    pub fn parse(key: &str) -> Result<KeyBinding, String> {
+        let mut modifiers = KeyModifiers::NONE;
+        let mut rest = key.trim();
+        while let Some((modifier, remaining)) = rest.split_once('+') {
+            if modifier.is_empty() {
+                break;
+            }
+            modifiers |= match modifier.to_lowercase().as_str() {
+                "ctrl" | "control" => KeyModifiers::CONTROL,
+                "alt" => KeyModifiers::ALT,
+                "shift" => KeyModifiers::SHIFT,
+                _ => return Err(format!("unknown modifier `{modifier}` in key `{key}`")),
             };
+            rest = remaining;
         }
-        Self::None
+        let mut chars = rest.chars();
+        let code = match (chars.next(), chars.next()) {
+            (Some(c), None) => KeyCode::Char(c),
+            _ => match rest.to_lowercase().as_str() {
+                "up" => KeyCode::Up,
+                "down" => KeyCode::Down,
+                "left" => KeyCode::Left,
+                "right" => KeyCode::Right,
+                "enter" | "return" => KeyCode::Enter,
+                "space" => KeyCode::Char(' '),
+                "esc" | "escape" => KeyCode::Esc,
+                "tab" => KeyCode::Tab,
+                "backspace" => KeyCode::Backspace,
+                "delete" => KeyCode::Delete,
+                "insert" => KeyCode::Insert,
+                "home" => KeyCode::Home,
+                "end" => KeyCode::End,
+                "pageup" => KeyCode::PageUp,
+                "pagedown" => KeyCode::PageDown,
+                name => match name.strip_prefix('f').map(|n| n.parse::<u8>()) {
+                    Some(Ok(n)) if (1..=12).contains(&n) => KeyCode::F(n),
+                    _ => return Err(format!("unknown key `{key}`")),
+                },
+            },
+        };
+        Ok(KeyBinding::new(code, modifiers))
+    }
+
+    /// Creates a binding, folding `Shift` into the character for
+    /// character keys so that `Shift+h` and `H` are the same binding.
+    pub fn new(code: KeyCode, modifiers: KeyModifiers) -> KeyBinding {
+        let mut modifiers =
+            modifiers & (KeyModifiers::CONTROL | KeyModifiers::ALT | KeyModifiers::SHIFT);
+        let code = match code {
+            KeyCode::Char(c) => {
+                let shifted = modifiers.contains(KeyModifiers::SHIFT);
+                modifiers.remove(KeyModifiers::SHIFT);
+                KeyCode::Char(if shifted { c.to_ascii_uppercase() } else { c })
+            }
+            code => code,
+        };
+        KeyBinding { code, modifiers }
+    }
+}
+
+impl From<KeyEvent> for KeyBinding {
+    fn from(event: KeyEvent) -> Self {
+        KeyBinding::new(event.code, event.modifiers)
+    }
+}
+
+impl fmt::Display for KeyBinding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.modifiers.contains(KeyModifiers::CONTROL) {
+            write!(f, "Ctrl+")?;
+        }
+        if self.modifiers.contains(KeyModifiers::ALT) {
+            write!(f, "Alt+")?;
+        }
+        if self.modifiers.contains(KeyModifiers::SHIFT) {
+            write!(f, "Shift+")?;
+        }
+        match self.code {
+            KeyCode::Char(' ') => write!(f, "Space"),
+            KeyCode::Char(c) => write!(f, "{c}"),
+            KeyCode::F(n) => write!(f, "F{n}"),
+            KeyCode::Esc => write!(f, "Esc"),
+            code => write!(f, "{code:?}"),
+        }
+    }
+}
+
+/// The active mapping of keys to controls. Loaded once at startup from
+/// `keys.toml` in the config directory and falls back to the default
+/// bindings.
+#[derive(Debug, Clone)]
+pub struct Keymap {
+    bindings: Vec<(KeyBinding, Control)>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        let defaults: Vec<(Control, Vec<String>)> = Control::BINDABLE
+            .iter()
+            .map(|c| (*c, c.default_keys().iter().map(|k| k.to_string()).collect()))
+            .collect();
+        Keymap::from_lists(&defaults).expect("default key bindings are valid")
+    }
+}
+
+impl Keymap {
+    /// Reads `keys.toml` from `config_dir`, writing the default bindings
+    /// to it first if it does not exist. Any control left out of the file
+    /// keeps those of its default keys that the file doesn't use.
+    pub fn read(config_dir: &str) -> Result<Keymap, String> {
+        let file = config_dir.to_string() + "/keys.toml";
+        if !path::Path::new(&file).exists() {
+            fs::create_dir_all(config_dir).ok();
+            if let Err(e) = fs::write(&file, Keymap::default_file()) {
+                return Err(e.to_string());
+            }
+        }
+        let file_content = fs::read_to_string(&file).unwrap_or_default();
+        Keymap::parse(&file_content).map_err(|e| format!("{file}: {e}"))
+    }
+
+    /// Parses the contents of a keys file, where each control name is
+    /// mapped to a list of keys.
+    pub fn parse(content: &str) -> Result<Keymap, String> {
+        let file: BTreeMap<String, Vec<String>> =
+            toml::from_str(content).map_err(|e| e.message().to_string())?;
+        // Controls added after the file was written get their default keys,
+        // except for keys the file already binds to something else.
+        let taken: Vec<KeyBinding> = file
+            .values()
+            .flatten()
+            .filter_map(|k| KeyBinding::parse(k).ok())
+            .collect();
+        let mut lists: Vec<(Control, Vec<String>)> = vec![];
+        for control in Control::BINDABLE {
+            let keys = match file.get(control.name()) {
+                Some(keys) => keys.clone(),
+                None => control
+                    .default_keys()
+                    .iter()
+                    .filter(|k| KeyBinding::parse(k).is_ok_and(|b| !taken.contains(&b)))
+                    .map(|k| k.to_string())
+                    .collect(),
+            };
+            lists.push((control, keys));
+        }
+        if let Some(name) = file
+            .keys()
+            .find(|name| !Control::BINDABLE.iter().any(|c| c.name() == *name))
+        {
+            return Err(format!("unknown control `{name}`"));
+        }
+        Keymap::from_lists(&lists)
+    }
+
+    fn from_lists(lists: &[(Control, Vec<String>)]) -> Result<Keymap, String> {
+        let mut bindings: Vec<(KeyBinding, Control)> = vec![];
+        for (control, keys) in lists {
+            if keys.is_empty() && matches!(control, Control::Select | Control::Quit) {
+                return Err(format!(
+                    "`{}` must be bound to at least one key",
+                    control.name()
+                ));
+            }
+            for key in keys {
+                let binding = KeyBinding::parse(key)?;
+                match bindings.iter().find(|(b, _)| *b == binding) {
+                    Some((_, c)) if c == control => continue,
+                    Some((_, c)) => {
+                        return Err(format!(
+                            "`{binding}` is bound to both `{}` and `{}`",
+                            c.name(),
+                            control.name()
+                        ))
+                    }
+                    None => bindings.push((binding, *control)),
+                }
+            }
+        }
+        Ok(Keymap { bindings })
+    }
+
+    fn default_file() -> String {
+        let mut content = String::from(
+            "# l1t key bindings\n\
+             #\n\
+             # Each control is mapped to a list of keys. Keys are single characters\n\
+             # or names like `Up`, `Enter`, `Space`, `Esc` or `F1` and can be\n\
+             # prefixed with `Ctrl+`, `Alt+` or `Shift+`.\n\n",
+        );
+        for control in Control::BINDABLE {
+            let keys: Vec<String> = control
+                .default_keys()
+                .iter()
+                .map(|k| format!("{k:?}"))
+                .collect();
+            content += &format!("{} = [{}]\n", control.name(), keys.join(", "));
+        }
+        content
+    }
+
+    /// Makes this the keymap used by `Control::read_input`. Only the first
+    /// keymap installed is used.
+    pub fn install(self) {
+        KEYMAP.set(self).ok();
+    }
+
+    /// The installed keymap, or the default bindings if none was installed.
+    pub fn active() -> &'static Keymap {
+        KEYMAP.get_or_init(Keymap::default)
+    }
+
+    pub fn control(&self, binding: &KeyBinding) -> Control {
+        self.bindings
+            .iter()
+            .find(|(b, _)| b == binding)
+            .map(|(_, c)| *c)
+            .unwrap_or(Control::None)
+    }
+
+    /// Every key bound to `control`, in the order they were declared.
+    pub fn keys(&self, control: Control) -> Vec<KeyBinding> {
+        self.bindings
+            .iter()
+            .filter(|(_, c)| *c == control)
+            .map(|(b, _)| *b)
+            .collect()
+    }
+
+    /// The keys bound to `control` joined for display, e.g. `Up, w, k`.
+    pub fn describe(&self, control: Control) -> String {
+        let keys: Vec<String> = self.keys(control).iter().map(|k| k.to_string()).collect();
+        keys.join(", ")
     }
 }
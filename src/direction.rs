@@ -1,7 +1,9 @@
+use serde::{Deserialize, Serialize};
+
 /// A cardinal direction (`UP`, `DOWN`, `LEFT`, or `RIGHT`) that a node
 /// can be facing/looking in. Contains the unit-coordinates of the direction
 /// relative to the node.
-#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Serialize, Deserialize)]
 pub struct Direction(pub i16, pub i16);
 
 impl Direction {
@@ -11,4 +13,9 @@ impl Direction {
     pub const RIGHT: Self = Self(0, 1);
     pub const FORWARD: Self = Self(0, 1);
     pub const BACKWARD: Self = Self(0, -1);
+
+    /// The direction pointing the opposite way.
+    pub fn opposite(&self) -> Self {
+        Self(-self.0, -self.1)
+    }
 }
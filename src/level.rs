@@ -1,10 +1,23 @@
-use crate::{controls::Control, direction::Direction, menu::*, node::*, repository::Repository};
+use crate::{
+    cache::Cache,
+    controls::Control,
+    direction::Direction,
+    menu::*,
+    moves::Move,
+    node::*,
+    repository::{LevelMeta, Repository, RepositoryError},
+    settings::Settings,
+    theme::Theme,
+};
 use crossterm::{
     cursor, execute,
     style::{Color, Print, SetBackgroundColor, SetForegroundColor, Stylize},
     terminal::{size, Clear, ClearType},
     ExecutableCommand,
 };
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use std::{
     fs,
     io::stdout,
@@ -18,28 +31,142 @@ pub enum LevelLossReason {
     Death,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum LevelSource {
     File(PathBuf),
     Url(String),
     Core(usize),
+    /// The level at `level` in the pack at `path`, counting from 0.
+    Pack {
+        path: PathBuf,
+        level: usize,
+    },
+    /// A level in a git repository, at `url` as it was at `commit`.
+    Git {
+        url: String,
+        commit: String,
+    },
+}
+
+impl LevelSource {
+    /// A short name that identifies the source and is safe to use
+    /// as a filename, e.g. `core-0`.
+    pub fn key(&self) -> String {
+        match self {
+            LevelSource::Core(level) => format!("core-{level}"),
+            LevelSource::File(path) => {
+                format!("file-{}", &checksum(&path.to_string_lossy())[0..16])
+            }
+            LevelSource::Url(url) | LevelSource::Git { url, .. } => {
+                format!("url-{}", &checksum(url)[0..16])
+            }
+            LevelSource::Pack { path, level } => {
+                format!("pack-{}-{level}", &checksum(&path.to_string_lossy())[0..16])
+            }
+        }
+    }
+
+    /// The URL a level from a repository or a file is recognised by in the
+    /// player's completed levels.
+    pub fn url(&self) -> Option<String> {
+        match self {
+            LevelSource::Url(url) | LevelSource::Git { url, .. } => Some(url.to_string()),
+            LevelSource::File(path) => Url::from_file_path(path).ok().map(|u| u.to_string()),
+            LevelSource::Core(_) | LevelSource::Pack { .. } => None,
+        }
+    }
+}
+
+/// The hex encoded SHA-256 hash of `content`.
+pub fn checksum(content: &str) -> String {
+    hex::encode(Sha256::digest(content.as_bytes()))
 }
 
 #[derive(Debug)]
 pub struct Level {
     pub info: LevelInfo,
+    pub options: LevelOptions,
     pub nodes: Vec<Node>,
     pub rows: u16,
     pub cols: u16,
     pub player_index: Option<usize>,
+    /// The number of times the player has moved.
+    pub moves: usize,
+    /// Every move played since the level was loaded, in order.
+    pub history: Vec<Move>,
+    /// The checksum of the level's options and grid, used to tell if the
+    /// level has changed since it was saved.
+    pub checksum: String,
 }
 
+/// How the player action (`Space` by default) affects the nodes
+/// next to the player.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Interaction {
+    /// Toggles every toggleable node next to the player at once.
+    All,
+    /// Waits for a direction and only toggles the node in that direction.
+    Single,
+}
+
+/// Options a level can declare with `key = value` lines between its
+/// header and its grid.
 #[derive(Debug, Clone)]
+pub struct LevelOptions {
+    pub interaction: Interaction,
+    /// The most moveable nodes the player can push in a line at once.
+    pub push_strength: usize,
+}
+
+impl Default for LevelOptions {
+    fn default() -> Self {
+        LevelOptions {
+            interaction: Interaction::All,
+            push_strength: 1,
+        }
+    }
+}
+
+impl LevelOptions {
+    /// Parses the option lines at the start of `content`, returning the
+    /// options and the remaining lines of the level grid.
+    fn parse<'a, 'b>(
+        content: &'a [&'b str],
+    ) -> Result<(LevelOptions, &'a [&'b str]), &'static str> {
+        let mut options = LevelOptions::default();
+        let mut lines = 0;
+        for line in content {
+            let (key, value) = match line.split_once('=') {
+                Some(option) if !line.starts_with('I') => option,
+                _ => break,
+            };
+            match (key.trim(), value.trim()) {
+                ("interaction", "all") => options.interaction = Interaction::All,
+                ("interaction", "single") => options.interaction = Interaction::Single,
+                ("interaction", _) => {
+                    return Err("The `interaction` option must be either `all` or `single`.")
+                }
+                ("push", value) => match value.parse() {
+                    Ok(n) => options.push_strength = n,
+                    Err(_) => return Err("The `push` option must be a whole number."),
+                },
+                _ => return Err("Unknown level option."),
+            }
+            lines += 1;
+        }
+        Ok((options, &content[lines..]))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LevelInfo {
     pub source: LevelSource,
     pub name: String,
     pub author: String,
     pub description: String,
+    /// Extra details given by the repository the level is from, if any.
+    #[serde(default, skip_serializing_if = "LevelMeta::is_empty")]
+    pub meta: LevelMeta,
 }
 
 #[derive(Debug)]
@@ -117,7 +244,7 @@ I1    X    SI
 IIIIIIIIIIIII",
     ];
 
-    fn draw_walls(&self, row_offset: u16, col_offset: u16) -> crossterm::Result<()> {
+    fn draw_walls(&self, row_offset: u16, col_offset: u16, theme: &Theme) -> crossterm::Result<()> {
         let mut stdout = stdout();
         for r in row_offset..(self.rows + row_offset) {
             for c in col_offset..(self.cols + col_offset) {
@@ -128,8 +255,8 @@ IIIIIIIIIIIII",
                 {
                     execute!(
                         stdout,
-                        SetForegroundColor(Color::White),
-                        SetBackgroundColor(Color::White),
+                        SetForegroundColor(theme.wall),
+                        SetBackgroundColor(theme.wall),
                         cursor::MoveTo(c, r),
                         Print('I'.bold()),
                     )?;
@@ -143,32 +270,60 @@ IIIIIIIIIIIII",
         )
     }
 
-    fn draw_nodes(&self, row_offset: u16, col_offset: u16) -> crossterm::Result<()> {
+    fn draw_nodes(&self, row_offset: u16, col_offset: u16, theme: &Theme) -> crossterm::Result<()> {
         for i in 0..self.nodes.len() {
-            self.nodes[i].draw((row_offset, col_offset))?;
+            self.nodes[i].draw((row_offset, col_offset), theme)?;
         }
         Ok(())
     }
 
-    fn draw_node_overlays(&self, row_offset: u16, col_offset: u16) -> crossterm::Result<()> {
+    fn draw_node_overlays(
+        &self,
+        row_offset: u16,
+        col_offset: u16,
+        theme: &Theme,
+    ) -> crossterm::Result<()> {
         for i in 0..self.nodes.len() {
-            self.nodes[i].draw_overlay((row_offset, col_offset))?;
+            self.nodes[i].draw_overlay((row_offset, col_offset), theme)?;
         }
         Ok(())
     }
 
+    /// The terminal `(row, col)` of the top left corner of the level.
+    fn offsets(&self) -> (u16, u16) {
+        let (term_cols, term_rows) = size().unwrap_or((0, 0));
+        (
+            term_rows.saturating_sub(self.rows) / 2,
+            term_cols.saturating_sub(self.cols) / 2,
+        )
+    }
+
     fn draw(&self) -> crossterm::Result<()> {
         let mut stdout = stdout();
         stdout.execute(Clear(ClearType::All))?;
-        let (term_cols, term_rows) = size().unwrap_or((0, 0));
-        let row_offset = (term_rows - self.rows) / 2;
-        let col_offset = (term_cols - self.cols) / 2;
-        self.draw_walls(row_offset, col_offset)?;
-        self.draw_node_overlays(row_offset, col_offset)?;
-        self.draw_nodes(row_offset, col_offset)?;
+        let (row_offset, col_offset) = self.offsets();
+        let theme = Theme::active();
+        self.draw_walls(row_offset, col_offset, &theme)?;
+        self.draw_node_overlays(row_offset, col_offset, &theme)?;
+        self.draw_nodes(row_offset, col_offset, &theme)?;
         Ok(())
     }
 
+    /// Draws `message` centered underneath the level.
+    fn draw_prompt(&self, message: &str) -> crossterm::Result<()> {
+        let (row_offset, _) = self.offsets();
+        let theme = Theme::active();
+        let (term_cols, _) = size().unwrap_or((0, 0));
+        execute!(
+            stdout(),
+            cursor::MoveTo(
+                term_cols.saturating_sub(message.len() as u16) / 2,
+                row_offset + self.rows + 1
+            ),
+            Print(theme.highlight().apply(message).bold()),
+        )
+    }
+
     fn set_lasers_shooting_at(&mut self) {
         for i in 0..self.nodes.len() {
             if let NodeType::Laser(l) = &self.nodes[i].node_type {
@@ -261,14 +416,94 @@ IIIIIIIIIIIII",
         let surrounding_nodes =
             &self.surrounding_nodes((self.nodes[player_index].row, self.nodes[player_index].col));
         for &i in surrounding_nodes.iter() {
-            if !self.nodes[i].is_player_toggleable()
-                || matches!(self.nodes[i].node_type, NodeType::Button(_))
-            {
-                continue;
+            self.toggle_node(i);
+        }
+    }
+
+    /// Performs the player action on the node at index `i` only, if the
+    /// player is able to toggle it.
+    fn toggle_node(&mut self, i: usize) {
+        if !self.nodes[i].is_player_toggleable()
+            || matches!(self.nodes[i].node_type, NodeType::Button(_))
+        {
+            return;
+        }
+        self.nodes[i].toggle();
+        if let NodeType::Switch(_) = &self.nodes[i].node_type {
+            self.toggle_blocks();
+        }
+    }
+
+    /// Performs the player action on just the node next to the player
+    /// in the direction `dir`.
+    fn player_action_in(&mut self, dir: Direction) {
+        let player_index = match self.player_index {
+            Some(i) => i,
+            None => return,
+        };
+        let pos = self.nodes[player_index].would_move_to(dir);
+        if let Some(i) = self.node_index_at(pos) {
+            self.toggle_node(i);
+        }
+    }
+
+    /// Handles a click at the terminal position `(col, row)`.
+    fn click(&mut self, col: u16, row: u16) {
+        let (row_offset, col_offset) = self.offsets();
+        if let (Some(row), Some(col)) = (row.checked_sub(row_offset), col.checked_sub(col_offset)) {
+            self.click_at(row, col);
+        }
+    }
+
+    /// Handles a click on the space at `(row, col)` of the grid. Clicking
+    /// a node next to the player toggles just that node.
+    pub fn click_at(&mut self, row: u16, col: u16) {
+        let player_index = match self.player_index {
+            Some(i) => i,
+            None => return,
+        };
+        let pos = (row, col);
+        let player_pos = (self.nodes[player_index].row, self.nodes[player_index].col);
+        if let Some(i) = self
+            .surrounding_nodes(player_pos)
+            .into_iter()
+            .find(|&i| self.nodes[i].row == pos.0 && self.nodes[i].col == pos.1)
+        {
+            let dir = Direction(
+                self.nodes[i].row as i16 - player_pos.0 as i16,
+                self.nodes[i].col as i16 - player_pos.1 as i16,
+            );
+            self.apply(Move::Toggle(dir));
+        }
+    }
+
+    /// Plays `m` and adds it to the level's history.
+    pub fn apply(&mut self, m: Move) {
+        match m {
+            Move::Step(dir) => self.move_player(dir),
+            Move::Action if self.options.interaction == Interaction::All => self.player_action(),
+            Move::Action => (),
+            Move::Toggle(dir) => self.player_action_in(dir),
+            Move::Pull => self.pull(),
+        }
+        self.history.push(m);
+    }
+
+    /// Plays `moves` without drawing the level, returning whether the
+    /// level is won by the last of them. Moves that lose the level or
+    /// carry on after it was won aren't a win.
+    pub fn replay(&mut self, moves: &[Move]) -> bool {
+        let mut moves = moves.iter();
+        loop {
+            self.reset_statues();
+            self.set_lasers_shooting_at();
+            let state = self.play_state();
+            if !state.is_playing {
+                return state.has_won && moves.next().is_none();
             }
-            self.nodes[i].toggle();
-            if let NodeType::Switch(_) = &self.nodes[i].node_type {
-                self.toggle_blocks();
+            match moves.next() {
+                Some(&m) => self.apply(m),
+                None => return false,
             }
         }
     }
@@ -291,29 +526,64 @@ IIIIIIIIIIIII",
         pos.0 >= 1 && pos.0 < self.rows - 1 && pos.1 >= 1 && pos.1 < self.cols - 1
     }
 
-    fn move_player(&mut self, dir: Direction) {
+    /// Moves the player one space in `dir`, pushing the line of moveable
+    /// nodes in front of them if it is no longer than the level's push
+    /// strength and there is an empty space behind it.
+    pub fn move_player(&mut self, dir: Direction) {
         let player_index = match self.player_index {
             Some(i) => i,
             None => return,
         };
+        if let NodeType::Player(p) = &mut self.nodes[player_index].node_type {
+            p.facing = dir;
+        }
         let new_pos = self.nodes[player_index].would_move_to(dir);
         if !self.is_valid_pos(new_pos) {
             return;
         }
-        if let Some(i) = self.node_index_at(new_pos) {
-            if !self.nodes[i].is_moveable() {
+        let mut pushed: Vec<usize> = vec![];
+        let mut next_pos = new_pos;
+        while let Some(i) = self.node_index_at(next_pos) {
+            if !self.nodes[i].is_moveable() || pushed.len() >= self.options.push_strength {
                 return;
             }
-            let new_pos = self.nodes[i].would_move_to(dir);
-            if !self.is_valid_pos(new_pos) {
-                return;
-            }
-            if self.node_index_at(new_pos).is_some() {
+            pushed.push(i);
+            next_pos = self.nodes[i].would_move_to(dir);
+            if !self.is_valid_pos(next_pos) {
                 return;
             }
+        }
+        for &i in pushed.iter() {
             self.nodes[i].move_in_dir(dir);
         }
         self.nodes[player_index].move_in_dir(dir);
+        self.moves += 1;
+    }
+
+    /// Steps the player backwards, away from the direction they are facing,
+    /// dragging the moveable node they are facing along with them.
+    fn pull(&mut self) {
+        let player_index = match self.player_index {
+            Some(i) => i,
+            None => return,
+        };
+        let facing = match &self.nodes[player_index].node_type {
+            NodeType::Player(p) => p.facing,
+            _ => return,
+        };
+        let facing_pos = self.nodes[player_index].would_move_to(facing);
+        let pulled_index = match self.node_index_at(facing_pos) {
+            Some(i) if self.nodes[i].is_moveable() => i,
+            _ => return,
+        };
+        let back = facing.opposite();
+        let new_pos = self.nodes[player_index].would_move_to(back);
+        if !self.is_valid_pos(new_pos) || self.node_index_at(new_pos).is_some() {
+            return;
+        }
+        self.nodes[player_index].move_in_dir(back);
+        self.nodes[pulled_index].move_in_dir(back);
+        self.moves += 1;
     }
 
     fn reset_statues(&mut self) {
@@ -324,6 +594,7 @@ IIIIIIIIIIIII",
         }
     }
 
+    #[allow(clippy::collapsible_match)]
     fn play_state(&self) -> PlayState {
         let mut all_statues_lit = true;
         for i in 0..self.nodes.len() {
@@ -370,6 +641,7 @@ IIIIIIIIIIIII",
         }
     }
 
+    /// The `.l1t` files in `level_dir`, in human sorted order.
     pub fn available_levels(level_dir: &Path) -> Result<Vec<LevelInfo>, String> {
         let files = match fs::read_dir(level_dir) {
             Ok(f) => f,
@@ -381,7 +653,10 @@ IIIIIIIIIIIII",
                 Ok(f) => f,
                 Err(e) => return Err(e.to_string()),
             };
-            filenames.push(f.path().to_path_buf());
+            let path = f.path();
+            if path.is_file() && path.extension().is_some_and(|e| e == "l1t") {
+                filenames.push(path);
+            }
         }
         let mut filenames: Vec<_> = filenames
             .iter()
@@ -395,17 +670,23 @@ IIIIIIIIIIIII",
                 Err(e) => return Err(e.to_string()),
             };
             let lines: Vec<&str> = content.split('\n').collect();
+            if lines.len() < 3 {
+                continue;
+            }
             levels.push(LevelInfo {
                 source: LevelSource::File(f.to_path_buf()),
                 name: lines[0].to_string(),
                 author: lines[1].to_string(),
                 description: lines[2].to_string(),
+                meta: LevelMeta::default(),
             });
         }
         Ok(levels)
     }
 
     fn parse_grid(content: &[&str], info: LevelInfo) -> Result<Level, &'static str> {
+        let checksum = checksum(&content.join("\n"));
+        let (options, content) = LevelOptions::parse(content)?;
         let rows = content.len() as u16;
         if rows < 3 {
             return Err("Level file must include a line for the `name`, `author`, `description`, and lines representing the level grid.");
@@ -436,10 +717,14 @@ IIIIIIIIIIIII",
         }
         Ok(Level {
             info,
+            options,
             nodes,
             rows,
             cols,
             player_index,
+            moves: 0,
+            history: vec![],
+            checksum,
         })
     }
 
@@ -452,33 +737,70 @@ IIIIIIIIIIIII",
             name: content[0].to_string(),
             author: content[1].to_string(),
             description: content[2].to_string(),
+            meta: LevelMeta::default(),
         };
         Level::parse_grid(&content[3..], info)
     }
 
+    /// Parses a full level, including its `name`, `author`, and
+    /// `description` lines.
+    pub fn parse(content: &str, source: LevelSource) -> Result<Level, &'static str> {
+        let content: Vec<&str> = content.trim().split('\n').collect();
+        Level::parse_full(&content, source)
+    }
+
     pub fn file(filename: PathBuf) -> Result<Level, &'static str> {
         let content: String = fs::read_to_string(&filename).unwrap_or_default();
-        let content: Vec<&str> = content.trim().split('\n').collect();
-        Level::parse_full(&content, LevelSource::File(filename))
+        Level::parse(&content, LevelSource::File(filename))
     }
 
-    pub async fn url(info: LevelInfo) -> Result<Level, &'static str> {
-        if let LevelSource::Url(url) = &info.source {
-            let content = match Repository::download_from_url(url.to_string()).await {
-                Ok(c) => c,
-                Err(_) => return Err("Error downloading level"),
-            };
-            let content: Vec<&str> = content.trim().split('\n').collect();
-            Level::parse_grid(&content, info)
-        } else {
-            Err("Level source was not a URL")
+    /// Downloads the level described by `info` from `repository`, using
+    /// the copy in `cache` if it's recent or the repository can't be
+    /// reached. If the listing gave the level's hash, the level must match
+    /// it, and otherwise the hash of what was downloaded is recorded in the
+    /// level's info. Levels listed from a directory of `.l1t` files are
+    /// read from their file, skipping the `name`, `author` and
+    /// `description` lines the listing was made from.
+    pub async fn url(
+        mut info: LevelInfo,
+        repository: &Repository,
+        cache: &Cache,
+    ) -> Result<Level, RepositoryError> {
+        let content = match &info.source {
+            LevelSource::Url(url) => repository.download(url, cache).await?.content,
+            LevelSource::Git { url, commit } => repository.git_level(url, commit).await?,
+            LevelSource::File(path) => {
+                fs::read_to_string(path).map_err(|e| RepositoryError::File {
+                    path: path.display().to_string(),
+                    error: e.to_string(),
+                })?
+            }
+            _ => {
+                return Err(RepositoryError::BadLevel(
+                    "Level source was not a URL".to_string(),
+                ))
+            }
+        };
+        let hash = checksum(&content);
+        match &info.meta.hash {
+            Some(expected) if !expected.eq_ignore_ascii_case(&hash) => {
+                return Err(RepositoryError::Integrity(format!(
+                    "{} doesn't match its hash in the listing",
+                    info.source.url().unwrap_or_default()
+                )))
+            }
+            _ => info.meta.hash = Some(hash),
         }
+        let content: Vec<&str> = content.trim().split('\n').collect();
+        let grid = match info.source {
+            LevelSource::File(_) => content.get(3..).unwrap_or_default(),
+            _ => &content,
+        };
+        Level::parse_grid(grid, info).map_err(|e| RepositoryError::BadLevel(e.to_string()))
     }
 
     pub fn core(level: usize) -> Result<Level, &'static str> {
-        let content = Level::CORE_LEVELS[level];
-        let content: Vec<&str> = content.trim().split('\n').collect();
-        Level::parse_full(&content, LevelSource::Core(level))
+        Level::parse(Level::CORE_LEVELS[level], LevelSource::Core(level))
     }
 
     pub fn play(&mut self) -> Result<LevelResult, &str> {
@@ -494,23 +816,40 @@ IIIIIIIIIIIII",
                 });
             }
             match Control::read_input() {
-                Control::Up => self.move_player(Direction::UP),
-                Control::Down => self.move_player(Direction::DOWN),
-                Control::Left => self.move_player(Direction::LEFT),
-                Control::Right => self.move_player(Direction::RIGHT),
-                Control::Action => self.player_action(),
+                Control::Up => self.apply(Move::Step(Direction::UP)),
+                Control::Down => self.apply(Move::Step(Direction::DOWN)),
+                Control::Left => self.apply(Move::Step(Direction::LEFT)),
+                Control::Right => self.apply(Move::Step(Direction::RIGHT)),
+                Control::Action => match self.options.interaction {
+                    Interaction::All => self.apply(Move::Action),
+                    Interaction::Single => {
+                        self.draw_prompt("CHOOSE A DIRECTION").ok();
+                        match Control::read_input() {
+                            Control::Up => self.apply(Move::Toggle(Direction::UP)),
+                            Control::Down => self.apply(Move::Toggle(Direction::DOWN)),
+                            Control::Left => self.apply(Move::Toggle(Direction::LEFT)),
+                            Control::Right => self.apply(Move::Toggle(Direction::RIGHT)),
+                            Control::Click(col, row) => self.click(col, row),
+                            _ => (),
+                        }
+                    }
+                },
+                Control::Click(col, row) => self.click(col, row),
+                Control::Pull => self.apply(Move::Pull),
                 Control::Help => {
                     Menu::open(MenuType::HelpMenu);
                 }
-                Control::Quit => {
-                    if let Some(Selection::Yes) =
-                        Menu::open(MenuType::YesNoSelection("Are you sure you want to quit?"))
-                    {
-                        return Ok(LevelResult {
-                            has_won: false,
-                            reason_for_loss: Some(LevelLossReason::Quit),
-                        });
-                    }
+                Control::Quit
+                    if !Settings::active().confirm_on_quit
+                        || matches!(
+                            Menu::open(MenuType::YesNoSelection("Are you sure you want to quit?")),
+                            Some(Selection::Yes)
+                        ) =>
+                {
+                    return Ok(LevelResult {
+                        has_won: false,
+                        reason_for_loss: Some(LevelLossReason::Quit),
+                    });
                 }
                 _ => (),
             }
@@ -3,10 +3,19 @@
 //! A strategy game about shooting laser beams and lighting up statues.
 //! Checkout out [the repo](https://github.com/alex-laycalvert/l1t) for
 //! more info.
+pub mod cache;
 pub mod direction;
 pub mod controls;
 pub mod repository;
 pub mod level;
 pub mod menu;
+pub mod moves;
 pub mod node;
+pub mod pack;
+pub mod paths;
+pub mod publish;
+pub mod save;
+pub mod server;
+pub mod settings;
+pub mod theme;
 pub mod userdata;
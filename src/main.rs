@@ -1,22 +1,31 @@
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use crossterm::{
-    cursor, execute,
+    cursor,
+    event::{DisableMouseCapture, EnableMouseCapture},
+    execute,
     terminal::{disable_raw_mode, enable_raw_mode, Clear, ClearType},
 };
-use home::home_dir;
+use l1t::controls::Keymap;
 use l1t::level::*;
 use l1t::menu::*;
-//use l1t::repository::*;
+use l1t::moves::Move;
+use l1t::pack::Pack;
+use l1t::paths::Paths;
+use l1t::publish::{build_repository, listed_levels};
+use l1t::repository::{self, ListingQuery, Repository};
+use l1t::save::SavedLevel;
+use l1t::server::LevelServer;
+use l1t::settings::Settings;
+use l1t::theme::Theme;
 use l1t::userdata::*;
 use std::{
     error::Error,
+    fs,
     io::stdout,
     path::{Path, PathBuf},
     thread, time,
 };
 
-const SLEEP_TIME: u64 = 500;
-
 /// A terminal based strategy game about shooting lasers and lighting statues
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
@@ -24,11 +33,80 @@ struct Args {
     /// The `.l1t` file to load a level from
     #[arg(short, long)]
     file: Option<PathBuf>,
+    /// The profile to play as, created if it doesn't exist. Defaults to
+    /// the last profile played
+    #[arg(short, long)]
+    profile: Option<String>,
+    /// Keep all config, progress and downloads in this directory instead
+    /// of the usual locations. Can also be set with `L1T_HOME`
+    #[arg(long)]
+    data_dir: Option<String>,
+    #[command(subcommand)]
+    command: Option<Command>,
     ///// Repository to download levels from
     //#[arg(short, long)]
     //repo_url: Option<String>,
 }
 
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Move your progress between computers
+    Progress {
+        #[command(subcommand)]
+        command: ProgressCommand,
+    },
+    /// Host your own levels in a repository
+    Repo {
+        #[command(subcommand)]
+        command: RepoCommand,
+    },
+    /// Share a directory of `.l1t` files as a repository over HTTP, e.g.
+    /// with other computers on your network
+    Serve {
+        /// The directory of `.l1t` files to share
+        dir: PathBuf,
+        /// The address to listen on
+        #[arg(short, long, default_value = "0.0.0.0:8000")]
+        addr: String,
+        /// Accept levels published with `l1t repo publish` and the results
+        /// of players who report them
+        #[arg(long)]
+        uploads: bool,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum ProgressCommand {
+    /// Write the profile's completed levels and stats to a file
+    Export { file: PathBuf },
+    /// Merge the progress in a file written by `export` into the profile
+    Import { file: PathBuf },
+}
+
+#[derive(Subcommand, Debug)]
+enum RepoCommand {
+    /// Build a repository that any static web server can host from a
+    /// directory of `.l1t` files
+    Build {
+        /// The directory of `.l1t` files to put in the repository
+        source: PathBuf,
+        /// The directory to write the repository to
+        out: PathBuf,
+    },
+    /// Upload a level to a repository that accepts them. You'll have to
+    /// win the level first, so the repository can check it can be won
+    Publish {
+        /// The `.l1t` file of the level
+        file: PathBuf,
+        /// The name of one of your repositories, or the URL of one
+        repository: String,
+        /// The moves that win the level, instead of winning it yourself,
+        /// e.g. `RRUx` (see REPO.md)
+        #[arg(long)]
+        solution: Option<String>,
+    },
+}
+
 enum PlayStatus<'a> {
     WonLevel,
     Quit,
@@ -38,13 +116,14 @@ enum PlayStatus<'a> {
 
 fn setup() -> crossterm::Result<()> {
     enable_raw_mode()?;
-    execute!(stdout(), cursor::Hide)
+    execute!(stdout(), cursor::Hide, EnableMouseCapture)
 }
 
 fn exit(error: Option<&str>) -> Result<(), Box<dyn Error>> {
     disable_raw_mode()?;
     execute!(
         stdout(),
+        DisableMouseCapture,
         cursor::Show,
         cursor::MoveTo(0, 0),
         Clear(ClearType::All)
@@ -65,115 +144,717 @@ async fn main() -> Result<(), Box<dyn Error>> {
     //.ok();
 
     let args = Args::parse();
+    let paths = Paths::new(args.data_dir);
+    if let Some(command) = args.command {
+        if let Err(e) = run_command(command, paths, args.profile).await {
+            eprintln!("Error: {e}");
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
     setup().ok();
+    match Keymap::read(&paths.config) {
+        Ok(keymap) => keymap.install(),
+        Err(e) => return exit(Some(&e)),
+    }
     if let Some(filename) = &args.file {
+        match UserData::read_settings(&paths, args.profile.clone())
+            .and_then(|s| Theme::load(&s.theme, &paths.config).map(|t| (s, t)))
+        {
+            Ok((settings, theme)) => {
+                settings.install();
+                theme.install();
+            }
+            Err(e) => return exit(Some(&e)),
+        }
         return play_file(filename);
     }
 
-    let home = match home_dir() {
-        Some(h) => h,
-        None => return exit(Some("failed to find user's home directory")),
-    };
-    let home = home.to_str().unwrap_or("");
-    let user_data = match UserData::read(home.to_string()) {
+    let temporary = paths.temporary.then(|| paths.data.to_string());
+    let mut user_data = match UserData::read(paths, args.profile) {
         Ok(d) => d,
         Err(e) => return exit(Some(&e)),
     };
+    if let Some(dir) = temporary {
+        user_data.warnings.push(format!(
+            "Couldn't find your home directory, so your progress is being kept in {dir}. Set L1T_HOME to choose where it's kept."
+        ));
+    }
 
     play(user_data).await
 }
 
+/// Runs a command from the command line, without starting the game.
+async fn run_command(
+    command: Command,
+    paths: Paths,
+    profile: Option<String>,
+) -> Result<(), String> {
+    match command {
+        Command::Progress { command } => {
+            let mut user_data = UserData::read(paths, profile)?;
+            for warning in user_data.warnings.drain(..) {
+                eprintln!("Warning: {warning}");
+            }
+            match command {
+                ProgressCommand::Export { file } => {
+                    user_data.export_progress(&file)?;
+                    println!(
+                        "Exported the progress of `{}` to {}",
+                        user_data.profile,
+                        file.display()
+                    );
+                }
+                ProgressCommand::Import { file } => {
+                    let added = user_data.import_progress(&file)?;
+                    println!(
+                        "Imported {added} new completed level(s) into `{}`",
+                        user_data.profile
+                    );
+                }
+            }
+        }
+        Command::Repo { command } => match command {
+            RepoCommand::Build { source, out } => {
+                let levels = build_repository(&source, &out)?;
+                println!(
+                    "Built a repository of {} level(s) in {}",
+                    levels.len(),
+                    out.display()
+                );
+                println!(
+                    "Host it with any static web server, e.g. `python -m http.server -d {}`",
+                    out.display()
+                );
+            }
+            RepoCommand::Publish {
+                file,
+                repository,
+                solution,
+            } => {
+                let content =
+                    fs::read_to_string(&file).map_err(|e| format!("{}: {e}", file.display()))?;
+                let mut level = Level::parse(&content, LevelSource::File(file.clone()))
+                    .map_err(|e| format!("{}: {e}", file.display()))?;
+                let repository = Repository::read_all(&paths.config)?
+                    .into_iter()
+                    .find(|r| r.name == repository)
+                    .unwrap_or_else(|| Repository::new(repository.clone(), repository));
+                let solution = match solution {
+                    Some(solution) => {
+                        let moves = Move::parse(&solution)?;
+                        if !level.replay(&moves) {
+                            return Err("the solution doesn't win the level".to_string());
+                        }
+                        moves
+                    }
+                    None => match win_level(&file, &paths, profile)? {
+                        Some(moves) => moves,
+                        None => return Err("the level has to be won to publish it".to_string()),
+                    },
+                };
+                let info = repository
+                    .upload_level(&content, &solution)
+                    .await
+                    .map_err(|e| e.to_string())?;
+                println!(
+                    "Published {} to {} as {}",
+                    level.info.name, repository.name, info.source
+                );
+            }
+        },
+        Command::Serve { dir, addr, uploads } => {
+            let mut server = LevelServer::bind(&dir, &addr)?;
+            if uploads {
+                server = server.accept_uploads();
+            }
+            let (levels, errors) = listed_levels(&dir)?;
+            for error in errors {
+                eprintln!("Warning: {error}");
+            }
+            let port = server.local_addr()?.port();
+            println!(
+                "Serving {} level(s) from {} on {addr}",
+                levels.len(),
+                dir.display()
+            );
+            println!(
+                "Add it as a repository with the URL http://localhost:{port} on this computer, or with this computer's address on others"
+            );
+            if uploads {
+                println!("Levels can be published to it with `l1t repo publish`");
+            }
+            server.run().await?;
+        }
+    }
+    Ok(())
+}
+
 async fn play(mut user_data: UserData) -> Result<(), Box<dyn Error>> {
+    for warning in user_data.warnings.drain(..) {
+        Menu::open(MenuType::Message(&warning));
+    }
     loop {
-        let selection = Menu::open(MenuType::MainSelection(&user_data.completed_core_levels))
-            .unwrap_or(Selection::Play(LevelSource::Core(0)));
+        let selection = Menu::open(MenuType::MainSelection(
+            &user_data.completed_core_levels,
+            user_data.saved_level().is_some(),
+        ))
+        .unwrap_or(Selection::Play(LevelSource::Core(0)));
         match selection {
             Selection::Play(level_source) => match level_source {
                 LevelSource::Core(level) => {
-                    let mut current_level = level;
-                    loop {
-                        if current_level >= Level::NUM_CORE_LEVELS {
-                            Menu::open(MenuType::Message(
-                                "You've completed all core levels, thanks for playing!",
-                            ));
-                            break;
-                        }
-                        let mut level = match Level::core(current_level) {
-                            Ok(l) => l,
-                            Err(e) => return exit(Some(e)),
-                        };
-                        let result = level.play();
-                        match handle_level_result(result) {
-                            PlayStatus::WonLevel => {
-                                if let Err(e) = user_data.complete(level.info) {
-                                    return exit(Some(&e));
-                                };
-                                current_level += 1;
-                            }
-                            PlayStatus::LostLevel => continue,
-                            PlayStatus::Quit => break,
-                            PlayStatus::Error(e) => return exit(Some(e)),
-                        }
+                    if let Err(e) = play_core(&mut user_data, level, None).await {
+                        return exit(Some(&e));
                     }
                 }
-                LevelSource::File(_) => {}
-                LevelSource::Url(_url) => {}
+                LevelSource::File(path) => {
+                    if let Err(e) = play_local_level(&mut user_data, path, None).await {
+                        return exit(Some(&e));
+                    }
+                }
+                LevelSource::Pack { path, level } => {
+                    if let Err(e) = open_pack(&mut user_data, &path, Some(level), None).await {
+                        return exit(Some(&e));
+                    }
+                }
+                LevelSource::Url(_) | LevelSource::Git { .. } => {}
             },
+            Selection::Resume => {
+                let saved = match user_data.saved_level() {
+                    Some(s) => s,
+                    None => continue,
+                };
+                let result = match &saved.info.source {
+                    LevelSource::Core(level) => {
+                        play_core(&mut user_data, *level, Some(saved)).await
+                    }
+                    LevelSource::Url(_) | LevelSource::Git { .. } => {
+                        play_repo_level(&mut user_data, saved.info.clone(), Some(saved)).await
+                    }
+                    LevelSource::File(path) => {
+                        play_local_level(&mut user_data, path.clone(), Some(saved)).await
+                    }
+                    LevelSource::Pack { path, level } => {
+                        let (path, level) = (path.clone(), *level);
+                        open_pack(&mut user_data, &path, Some(level), Some(saved)).await
+                    }
+                };
+                if let Err(e) = result {
+                    return exit(Some(&e));
+                }
+            }
             Selection::Repository => {
-                if user_data.repositories.is_empty() {
+                if let Err(e) = manage_repositories(&mut user_data).await {
+                    return exit(Some(&e));
+                }
+            }
+            Selection::Profile => {
+                if let Err(e) = manage_profiles(&mut user_data) {
+                    return exit(Some(&e));
+                }
+            }
+            Selection::Settings => {
+                let mut settings = user_data.settings.clone();
+                Menu::open(MenuType::Settings(&mut settings, &user_data.themes()));
+                if settings != user_data.settings {
+                    if let Err(e) = user_data.save_settings(settings) {
+                        Menu::open(MenuType::Message(&e));
+                    }
+                }
+            }
+            Selection::Help => {
+                Menu::open(MenuType::HelpMenu);
+            }
+            _ => break,
+        }
+    }
+    exit(None)
+}
+
+/// Opens the repository menu, where the player can pick a repository to
+/// play levels from or add, edit, delete and reorder repositories.
+async fn manage_repositories(user_data: &mut UserData) -> Result<(), String> {
+    let mut selected = 0;
+    loop {
+        match Menu::open(MenuType::RepositorySelection(
+            &user_data.repositories,
+            selected,
+        )) {
+            Some(Selection::Item(i)) => {
+                selected = i;
+                if !user_data.repositories[i].enabled {
                     Menu::open(MenuType::Message(
-                        "You don't have any repositories setup in $HOME/.l1t/repositories.l1t_conf",
+                        "This repository is disabled in your repositories.toml file",
                     ));
                     continue;
                 }
-                while let Some(Selection::Item(i)) =
-                    Menu::open(MenuType::RepositorySelection(&user_data.repositories))
-                {
-                    if let Err(e) = user_data.repositories[i].download_listing().await {
-                        Menu::open(MenuType::Message(&e.to_string()));
+                if let Some(dir) = user_data.repositories[i].local_dir() {
+                    if Pack::is_pack(&dir) {
+                        open_pack(user_data, &dir, None, None).await?;
                         continue;
-                    };
-                    loop {
-                        if let Some(selection) = Menu::open(MenuType::RepositoryLevelSelection(
-                            user_data.repositories[i].clone(),
-                            &user_data.completed_levels,
-                        )) {
-                            match selection {
-                                Selection::Item(j) => {
-                                    let level_info = &user_data.repositories[i].levels[j];
-                                    loop {
-                                        let mut level = match Level::url(level_info.clone()).await {
-                                            Ok(l) => l,
-                                            Err(e) => return exit(Some(e)),
-                                        };
-                                        let result = level.play();
-                                        match handle_level_result(result) {
-                                            PlayStatus::WonLevel => {
-                                                if let Err(e) = user_data.complete(level.info) {
-                                                    return exit(Some(&e));
-                                                };
-                                                break;
-                                            }
-                                            PlayStatus::LostLevel => continue,
-                                            PlayStatus::Quit => break,
-                                            PlayStatus::Error(e) => return exit(Some(e)),
-                                        }
+                    }
+                }
+                if let Err(e) = user_data.repositories[i]
+                    .download_listing(&user_data.cache)
+                    .await
+                {
+                    let message = format!("Couldn't open {}: {e}", user_data.repositories[i].name);
+                    Menu::open(MenuType::Message(&message));
+                    continue;
+                };
+                let mut search = String::new();
+                let mut shown = user_data.repositories[i].clone();
+                loop {
+                    if let Some(selection) = Menu::open(MenuType::RepositoryLevelSelection(
+                        shown.clone(),
+                        &user_data.completed_levels,
+                        &search,
+                    )) {
+                        match selection {
+                            Selection::Item(j) => {
+                                let level_info = shown.levels[j].clone();
+                                play_repo_level(user_data, level_info, None).await?;
+                            }
+                            Selection::Search => {
+                                let Some(Selection::Text(input)) = Menu::open(MenuType::TextInput(
+                                    "SEARCH LEVELS (tag:name FOR TAGS)",
+                                    search.to_string(),
+                                )) else {
+                                    continue;
+                                };
+                                let query = ListingQuery::parse(&input);
+                                if query.is_empty() {
+                                    search = String::new();
+                                    shown = user_data.repositories[i].clone();
+                                    continue;
+                                }
+                                match user_data.repositories[i]
+                                    .search(&query, &user_data.cache)
+                                    .await
+                                {
+                                    Ok(results) => {
+                                        search = input.trim().to_string();
+                                        shown = results;
+                                    }
+                                    Err(e) => {
+                                        let message = format!("Couldn't search: {e}");
+                                        Menu::open(MenuType::Message(&message));
                                     }
                                 }
-                                Selection::Quit => break,
-                                _ => continue,
                             }
+                            Selection::Quit => break,
+                            _ => continue,
                         }
                     }
                 }
             }
-            Selection::Help => {
-                Menu::open(MenuType::HelpMenu);
+            Some(Selection::New) => {
+                let repository = Repository::new(String::new(), String::new());
+                if let Some(repository) = edit_repository(user_data, repository, None).await {
+                    user_data.repositories.push(repository);
+                    user_data.save_repositories()?;
+                    selected = user_data.repositories.len() - 1;
+                }
             }
-            _ => break,
+            Some(Selection::Edit(i)) => {
+                selected = i;
+                let repository = user_data.repositories[i].clone();
+                if let Some(repository) = edit_repository(user_data, repository, Some(i)).await {
+                    user_data.repositories[i] = repository;
+                    user_data.save_repositories()?;
+                }
+            }
+            Some(Selection::Download(i)) => {
+                selected = i;
+                let repository = &mut user_data.repositories[i];
+                if repository.local_dir().is_some() {
+                    let message = format!(
+                        "{} is on this computer, so it can always be played offline",
+                        repository.name
+                    );
+                    Menu::open(MenuType::Message(&message));
+                    continue;
+                }
+                let message = match repository.download_all(&user_data.cache).await {
+                    Ok(downloaded) => format!(
+                        "Downloaded {downloaded} of {} levels from {} for offline play",
+                        repository.levels.len(),
+                        repository.name
+                    ),
+                    Err(e) => format!("Couldn't download {}: {e}", repository.name),
+                };
+                Menu::open(MenuType::Message(&message));
+            }
+            Some(Selection::Delete(i)) => {
+                selected = i;
+                let message = format!("Remove {}?", user_data.repositories[i].name);
+                if let Some(Selection::Yes) = Menu::open(MenuType::YesNoSelection(&message)) {
+                    user_data.repositories.remove(i);
+                    user_data.save_repositories()?;
+                    selected = i.min(user_data.repositories.len().saturating_sub(1));
+                }
+            }
+            Some(Selection::MoveUp(i)) => {
+                selected = i;
+                if i > 0 {
+                    user_data.repositories.swap(i, i - 1);
+                    user_data.save_repositories()?;
+                    selected = i - 1;
+                }
+            }
+            Some(Selection::MoveDown(i)) => {
+                selected = i;
+                if i + 1 < user_data.repositories.len() {
+                    user_data.repositories.swap(i, i + 1);
+                    user_data.save_repositories()?;
+                    selected = i + 1;
+                }
+            }
+            _ => return Ok(()),
         }
     }
-    exit(None)
+}
+
+/// Asks the player for a name and URL for `repository`, then checks that
+/// the URL points to a repository before returning it. `index` is the
+/// position of the repository being edited, if it's already configured.
+/// Returns `None` if the player cancels.
+async fn edit_repository(
+    user_data: &UserData,
+    mut repository: Repository,
+    index: Option<usize>,
+) -> Option<Repository> {
+    let name = match Menu::open(MenuType::TextInput(
+        "REPOSITORY NAME",
+        repository.name.to_string(),
+    )) {
+        Some(Selection::Text(name)) => name.trim().to_string(),
+        _ => return None,
+    };
+    if name.is_empty() {
+        Menu::open(MenuType::Message("A repository needs a name"));
+        return None;
+    }
+    let taken = user_data
+        .repositories
+        .iter()
+        .enumerate()
+        .any(|(i, r)| r.name == name && Some(i) != index);
+    if taken {
+        Menu::open(MenuType::Message(
+            "A repository with that name already exists",
+        ));
+        return None;
+    }
+    let url = match Menu::open(MenuType::TextInput(
+        "REPOSITORY URL",
+        repository.url.to_string(),
+    )) {
+        Some(Selection::Text(url)) => url.trim().trim_end_matches('/').to_string(),
+        _ => return None,
+    };
+    let is_url = ["http://", "https://", "file://"]
+        .iter()
+        .any(|scheme| url.starts_with(scheme));
+    if !is_url && repository::local_path(&url).is_none() {
+        Menu::open(MenuType::Message(
+            "A repository URL must start with http://, https:// or file://, or be a path",
+        ));
+        return None;
+    }
+    // The levels listed from where the repository used to be aren't its
+    // levels anymore. Its kind and rev aren't edited here, so only a new
+    // URL changes where they come from.
+    if url != repository.url {
+        repository.forget_levels();
+    }
+    repository.name = name;
+    repository.url = url;
+    let checked = match repository.local_dir().filter(|dir| Pack::is_pack(dir)) {
+        Some(dir) => Pack::open(&dir).map(|_| ()),
+        None => repository
+            .validate(&user_data.cache)
+            .await
+            .map_err(|e| e.to_string()),
+    };
+    if let Err(e) = checked {
+        Menu::open(MenuType::Message(&format!(
+            "Couldn't check {}: {e}",
+            repository.name
+        )));
+        if !matches!(
+            Menu::open(MenuType::YesNoSelection("Save it anyway?")),
+            Some(Selection::Yes)
+        ) {
+            return None;
+        }
+    }
+    Some(repository)
+}
+
+/// Opens the profile menu, where the player can switch to, create, copy,
+/// or reset profiles.
+fn manage_profiles(user_data: &mut UserData) -> Result<(), String> {
+    loop {
+        let profiles = user_data.profiles();
+        let mut options: Vec<String> = profiles
+            .iter()
+            .map(|p| {
+                if *p == user_data.profile {
+                    format!("{p} (current)")
+                } else {
+                    p.to_string()
+                }
+            })
+            .collect();
+        options.push("+ New Profile".to_string());
+        let i = match Menu::open(MenuType::Selection("PROFILES", options)) {
+            Some(Selection::Item(i)) => i,
+            _ => return Ok(()),
+        };
+        if i == profiles.len() {
+            if let Some(Selection::Text(name)) =
+                Menu::open(MenuType::TextInput("NEW PROFILE NAME", String::new()))
+            {
+                if let Err(e) = UserData::validate_profile_name(&name) {
+                    Menu::open(MenuType::Message(&e));
+                } else if profiles.contains(&name) {
+                    Menu::open(MenuType::Message("A profile with that name already exists"));
+                } else {
+                    user_data.switch_profile(&name)?;
+                }
+            }
+            continue;
+        }
+        let profile = &profiles[i];
+        let actions = vec![
+            "Switch To".to_string(),
+            "Copy".to_string(),
+            "Reset".to_string(),
+        ];
+        match Menu::open(MenuType::Selection(profile, actions)) {
+            Some(Selection::Item(0)) => {
+                user_data.switch_profile(profile)?;
+                for warning in user_data.warnings.drain(..) {
+                    Menu::open(MenuType::Message(&warning));
+                }
+            }
+            Some(Selection::Item(1)) => {
+                if let Some(Selection::Text(name)) =
+                    Menu::open(MenuType::TextInput("COPY PROFILE AS", String::new()))
+                {
+                    if let Err(e) = user_data.copy_profile(profile, &name) {
+                        Menu::open(MenuType::Message(&e));
+                    }
+                }
+            }
+            Some(Selection::Item(2)) => {
+                let message = format!("Reset all progress for {profile}?");
+                if let Some(Selection::Yes) = Menu::open(MenuType::YesNoSelection(&message)) {
+                    user_data.reset_profile(profile)?;
+                }
+            }
+            _ => (),
+        }
+    }
+}
+
+/// Restores `saved` onto the freshly loaded `level`, telling the player
+/// and discarding the save if the level has changed since it was saved.
+fn resume(user_data: &UserData, level: &mut Level, saved: SavedLevel) {
+    if !saved.restore(level) {
+        user_data.discard_save(&level.info.source);
+        Menu::open(MenuType::Message(
+            "This level has changed since you saved it, so it will start over.",
+        ));
+    }
+}
+
+/// Plays the level loaded by `load` until the player wins or quits,
+/// loading it again after every loss, and returns whether it was won.
+/// `load` returns `None` if the level couldn't be loaded, after telling the
+/// player why. `saved` is resumed the first time the level is loaded, and
+/// `on_win` is given the won level and how long it took to win before the
+/// level is marked as completed.
+async fn play_level(
+    user_data: &mut UserData,
+    mut load: impl AsyncFnMut(&UserData) -> Result<Option<Level>, String>,
+    mut saved: Option<SavedLevel>,
+    on_win: impl AsyncFnOnce(&Level, time::Duration),
+) -> Result<bool, String> {
+    loop {
+        let Some(mut level) = load(user_data).await? else {
+            return Ok(false);
+        };
+        if let Some(saved) = saved.take() {
+            resume(user_data, &mut level, saved);
+        }
+        let started = time::Instant::now();
+        let result = level.play();
+        match handle_level_result(result) {
+            PlayStatus::WonLevel => {
+                user_data.discard_save(&level.info.source);
+                user_data.record_result(&level, true)?;
+                on_win(&level, started.elapsed()).await;
+                user_data.complete(level.info)?;
+                return Ok(true);
+            }
+            PlayStatus::LostLevel => {
+                user_data.discard_save(&level.info.source);
+                user_data.record_result(&level, false)?;
+            }
+            PlayStatus::Quit => {
+                user_data.save_level(level)?;
+                return Ok(false);
+            }
+            PlayStatus::Error(e) => return Err(e.to_string()),
+        }
+    }
+}
+
+/// Plays the core levels in order starting from `current_level` until the
+/// player quits or completes them all. `saved` is resumed for the first level.
+async fn play_core(
+    user_data: &mut UserData,
+    mut current_level: usize,
+    mut saved: Option<SavedLevel>,
+) -> Result<(), String> {
+    loop {
+        if current_level >= Level::NUM_CORE_LEVELS {
+            Menu::open(MenuType::Message(
+                "You've completed all core levels, thanks for playing!",
+            ));
+            break;
+        }
+        let load = async |_: &UserData| Ok(Some(Level::core(current_level)?));
+        let won = play_level(user_data, load, saved.take(), async |_, _| {}).await?;
+        if !won || !user_data.settings.auto_advance {
+            break;
+        }
+        current_level += 1;
+    }
+    Ok(())
+}
+
+/// Plays a repository level until the player wins or quits. `saved` is
+/// resumed the first time the level is loaded.
+async fn play_repo_level(
+    user_data: &mut UserData,
+    level_info: LevelInfo,
+    saved: Option<SavedLevel>,
+) -> Result<(), String> {
+    // Levels from repositories that are no longer set up are downloaded
+    // without any of the repository's options.
+    let repository = level_info
+        .source
+        .url()
+        .and_then(|url| user_data.repository_for(&url).cloned())
+        .unwrap_or_else(|| Repository::new(String::new(), String::new()));
+    let load = async |user_data: &UserData| {
+        let level = Level::url(level_info.clone(), &repository, &user_data.cache).await;
+        match level {
+            Ok(level) => Ok(Some(level)),
+            Err(e) => {
+                Menu::open(MenuType::Message(&format!(
+                    "Couldn't load {}: {e}",
+                    level_info.name
+                )));
+                Ok(None)
+            }
+        }
+    };
+    let report = async |level: &Level, time| {
+        if !repository.report_results {
+            return;
+        }
+        let report = repository
+            .report_result(&level.info, level.history.len(), time)
+            .await;
+        if let Err(e) = report {
+            Menu::open(MenuType::Message(&format!(
+                "Couldn't report your result to {}: {e}",
+                repository.name
+            )));
+        }
+    };
+    play_level(user_data, load, saved, report).await?;
+    Ok(())
+}
+
+/// Opens the pack at `path`, starting at `level` if it's given, or letting
+/// the player choose a level from the pack otherwise. `saved` is resumed
+/// for the first level. Packs that can't be read are reported to the
+/// player rather than returned as errors.
+async fn open_pack(
+    user_data: &mut UserData,
+    path: &Path,
+    level: Option<usize>,
+    saved: Option<SavedLevel>,
+) -> Result<(), String> {
+    let pack = match Pack::open(path) {
+        Ok(pack) => pack,
+        Err(e) => {
+            if let Some(saved) = saved {
+                user_data.discard_save(&saved.info.source);
+            }
+            Menu::open(MenuType::Message(&format!("Couldn't open the pack, {e}")));
+            return Ok(());
+        }
+    };
+    if let Some(level) = level {
+        return play_pack(user_data, &pack, level, saved).await;
+    }
+    let completed = user_data.completed_in(&pack);
+    if let Some(Selection::Item(level)) =
+        Menu::open(MenuType::PackLevelSelection(&pack, &completed))
+    {
+        play_pack(user_data, &pack, level, None).await?;
+    }
+    Ok(())
+}
+
+/// Plays the levels of `pack` in order starting from `current_level`, like
+/// `play_core`. `saved` is resumed for the first level.
+async fn play_pack(
+    user_data: &mut UserData,
+    pack: &Pack,
+    mut current_level: usize,
+    mut saved: Option<SavedLevel>,
+) -> Result<(), String> {
+    loop {
+        if current_level >= pack.levels.len() {
+            let message = format!("You've completed {}, thanks for playing!", pack.name);
+            Menu::open(MenuType::Message(&message));
+            break;
+        }
+        let load = async |_: &UserData| Ok(Some(pack.level(current_level)?));
+        let won = play_level(user_data, load, saved.take(), async |_, _| {}).await?;
+        if !won || !user_data.settings.auto_advance {
+            break;
+        }
+        current_level += 1;
+    }
+    Ok(())
+}
+
+/// Plays the level in the file at `path` until the player wins or quits.
+/// `saved` is resumed the first time the level is loaded.
+async fn play_local_level(
+    user_data: &mut UserData,
+    path: PathBuf,
+    saved: Option<SavedLevel>,
+) -> Result<(), String> {
+    let load = async |user_data: &UserData| match Level::file(path.clone()) {
+        Ok(level) => Ok(Some(level)),
+        Err(e) => {
+            user_data.discard_save(&LevelSource::File(path.clone()));
+            let message = format!("Couldn't load {}: {e}", path.display());
+            Menu::open(MenuType::Message(&message));
+            Ok(None)
+        }
+    };
+    play_level(user_data, load, saved, async |_, _| {}).await?;
+    Ok(())
 }
 
 fn play_file(filename: &Path) -> Result<(), Box<dyn Error>> {
@@ -192,22 +873,59 @@ fn play_file(filename: &Path) -> Result<(), Box<dyn Error>> {
     exit(None)
 }
 
-fn handle_level_result(result: Result<LevelResult, &str>) -> PlayStatus {
+/// Has the player win the level at `filename` to prove that it can be
+/// won, returning the moves it was won with, or `None` if they quit.
+fn win_level(
+    filename: &Path,
+    paths: &Paths,
+    profile: Option<String>,
+) -> Result<Option<Vec<Move>>, String> {
+    let keymap = Keymap::read(&paths.config)?;
+    let settings = UserData::read_settings(paths, profile)?;
+    Theme::load(&settings.theme, &paths.config)?.install();
+    settings.install();
+    keymap.install();
+    setup().map_err(|e| e.to_string())?;
+    let solution = loop {
+        let mut level = match Level::file(filename.to_path_buf()) {
+            Ok(l) => l,
+            Err(e) => {
+                exit(None).ok();
+                return Err(e.to_string());
+            }
+        };
+        let result = level.play();
+        match handle_level_result(result) {
+            PlayStatus::WonLevel => break Some(level.history),
+            PlayStatus::Quit => break None,
+            PlayStatus::LostLevel => continue,
+            PlayStatus::Error(e) => {
+                exit(None).ok();
+                return Err(e.to_string());
+            }
+        }
+    };
+    exit(None).map_err(|e| e.to_string())?;
+    Ok(solution)
+}
+
+fn handle_level_result(result: Result<LevelResult, &str>) -> PlayStatus<'_> {
+    let delay = time::Duration::from_millis(Settings::active().animation_delay);
     match result {
         Ok(result) => {
             if result.has_won {
-                thread::sleep(time::Duration::from_millis(SLEEP_TIME));
+                thread::sleep(delay);
                 Menu::open(MenuType::Message("YAY, You Won!"));
                 PlayStatus::WonLevel
             } else if let Some(r) = result.reason_for_loss {
                 match r {
                     LevelLossReason::Zapper => {
-                        thread::sleep(time::Duration::from_millis(SLEEP_TIME));
+                        thread::sleep(delay);
                         Menu::open(MenuType::Message("Uh oh, you lit a zapper!"));
                         PlayStatus::LostLevel
                     }
                     LevelLossReason::Death => {
-                        thread::sleep(time::Duration::from_millis(SLEEP_TIME));
+                        thread::sleep(delay);
                         Menu::open(MenuType::Message("Uh oh, you got shot by a laser beam!"));
                         PlayStatus::LostLevel
                     }
@@ -1,29 +1,46 @@
 use crate::{
-    controls::Control,
+    controls::{Control, Keymap, TextInput},
     level::{Level, LevelSource},
-    repository::Repository,
+    pack::Pack,
+    repository::{LevelMeta, Repository},
+    settings::Settings,
+    theme::Theme,
     userdata::CompletedRepoLevel,
 };
 use crossterm::{
     cursor::MoveTo,
     execute,
     style::{
-        Attribute, Color, Print, ResetColor, SetAttribute, SetBackgroundColor, SetForegroundColor,
-        StyledContent, Stylize,
+        Attribute, Color, ContentStyle, Print, ResetColor, SetAttribute, SetBackgroundColor,
+        SetForegroundColor, SetStyle, StyledContent, Stylize,
     },
     terminal::{size, Clear, ClearType},
 };
-use std::io::stdout;
+use std::{
+    io::stdout,
+    time::{SystemTime, UNIX_EPOCH},
+};
 
 #[derive(Clone)]
 pub enum Selection {
     Play(LevelSource),
+    Resume,
     Repository,
+    Profile,
+    Settings,
     Help,
     Quit,
     Yes,
     No,
     Item(usize),
+    Text(String),
+    New,
+    Edit(usize),
+    Delete(usize),
+    MoveUp(usize),
+    MoveDown(usize),
+    Download(usize),
+    Search,
 }
 
 pub enum MenuType<'a> {
@@ -39,6 +56,11 @@ pub enum MenuType<'a> {
     /// `Yes` or `No`.
     YesNoSelection(&'a str),
 
+    /// Dialog box that displays the message and lets the player type
+    /// a line of text, starting with the given `String`. Returns a
+    /// `Selection::Text` on enter, or `None` if cancelled with `Esc`.
+    TextInput(&'a str, String),
+
     /// Same as `Message` but displays the entire help menu for
     /// the application in a `ScrollableMenu`.
     HelpMenu,
@@ -49,18 +71,24 @@ pub enum MenuType<'a> {
     /// the list of chunks to print.
     ScrollableMenu(Vec<Vec<StyledContent<&'a str>>>),
 
+    /// Lists the player's settings and lets them change each one with
+    /// the left and right controls, choosing the theme from the given
+    /// theme names. The changes are made to the given `Settings`, and
+    /// it's up to the caller to save them once the menu is closed.
+    Settings(&'a mut Settings, &'a [String]),
+
     /// Draws the `Main Menu` of the application with the logo
     /// and selections for `Play`, `Help`, and `Quit`. Must
     /// provide a `Vec<usize>` representing the core levels the
-    /// player has completed and a `Vec<Repository>` which is
-    /// the list of available repositories the user has.
+    /// player has completed and whether the player has a saved
+    /// level to resume, which adds a `Resume` selection.
     ///
     /// Selecting `Play` will open the `CoreLevelSelection` and
     /// will return a `Selection::Play(l)` where `l` is the selected
     /// level. Selecting `Repository` or `Online` from the menu will
     /// return a `Selection::Play(l)` where `l` is the selected repository
     /// level.
-    MainSelection(&'a Vec<usize>),
+    MainSelection(&'a Vec<usize>, bool),
 
     /// Draws the `Core Level` selection menu for the player
     /// to choose one of the built-in levels. Must be provided
@@ -70,19 +98,30 @@ pub enum MenuType<'a> {
 
     /// Draws the `Repository` selection menu to allow
     /// the player to select which repo they want to play
-    /// a level from. After selecting a repo, the `RepositoryLevelSelection`
+    /// a level from, starting with the repo at the given index
+    /// selected. After selecting a repo, the `RepositoryLevelSelection`
     /// is opened to select the url of the level.
-    RepositorySelection(&'a Vec<Repository>),
+    ///
+    /// The player can also ask to add a repo or to edit, delete, move or
+    /// download the selected one, which returns `Selection::New`,
+    /// `Selection::Edit`, `Selection::Delete`, `Selection::MoveUp`,
+    /// `Selection::MoveDown` or `Selection::Download` for the caller to
+    /// carry out.
+    RepositorySelection(&'a [Repository], usize),
 
-    RepositoryLevelSelection(Repository, &'a Vec<CompletedRepoLevel>),
-}
+    /// Lists the levels of the given `Repository`, marking the ones in
+    /// the `CompletedRepoLevel`s, and whether the listing is an old copy
+    /// used because the repository couldn't be reached. The `str` is the
+    /// search the levels were found with, if any. The details of the
+    /// selected level are shown below the list, and the player can ask to
+    /// search, which returns `Selection::Search`.
+    RepositoryLevelSelection(Repository, &'a Vec<CompletedRepoLevel>, &'a str),
 
-const RED: Color = Color::Rgb { r: 255, g: 0, b: 0 };
-const YELLOW: Color = Color::Rgb {
-    r: 255,
-    g: 255,
-    b: 0,
-};
+    /// Draws the levels of the given `Pack` like the `CoreLevelSelection`,
+    /// where each level is unlocked by completing the one before it. Must
+    /// be provided the levels of the pack the player has completed.
+    PackLevelSelection(&'a Pack, &'a Vec<usize>),
+}
 
 pub struct Menu;
 
@@ -93,12 +132,17 @@ impl Menu {
         start_col: u16,
         end_col: u16,
     ) -> crossterm::Result<()> {
+        let (horizontal, vertical, corners) = if Settings::active().ascii_only {
+            ("-", "|", ["+", "+", "+", "+"])
+        } else {
+            ("─", "│", ["┌", "┐", "└", "┘"])
+        };
         for r in start_row..=end_row {
             for c in start_col..=end_col {
                 if r == start_row || r == end_row {
-                    execute!(stdout(), MoveTo(c, r), Print("─"),)?;
+                    execute!(stdout(), MoveTo(c, r), Print(horizontal),)?;
                 } else if c == start_col || c == end_col {
-                    execute!(stdout(), MoveTo(c, r), Print("│"),)?;
+                    execute!(stdout(), MoveTo(c, r), Print(vertical),)?;
                 } else {
                     execute!(stdout(), MoveTo(c, r), Print(" "),)?;
                 }
@@ -107,29 +151,260 @@ impl Menu {
         execute!(
             stdout(),
             MoveTo(start_col, start_row),
-            Print("┌"),
+            Print(corners[0]),
             MoveTo(end_col, start_row),
-            Print("┐"),
+            Print(corners[1]),
             MoveTo(start_col, end_row),
-            Print("└"),
+            Print(corners[2]),
             MoveTo(end_col, end_row),
-            Print("┘"),
+            Print(corners[3]),
         )
     }
 
+    /// Turns a click on one of a menu's items into selecting that item.
+    /// `item_at` maps the clicked `(column, row)` to the index of the item
+    /// drawn there, if any.
+    pub fn select_clicked(
+        control: Control,
+        current_selection: &mut usize,
+        item_at: impl Fn(u16, u16) -> Option<usize>,
+    ) -> Control {
+        match control {
+            Control::Click(col, row) => match item_at(col, row) {
+                Some(i) => {
+                    *current_selection = i;
+                    Control::Select
+                }
+                None => Control::None,
+            },
+            control => control,
+        }
+    }
+
+    /// The first `len` characters of `text`, for fitting it in a column.
+    fn truncate(text: &str, len: usize) -> String {
+        text.chars().take(len).collect()
+    }
+
+    /// The index of the first of `rows` items to draw so that the item at
+    /// `selection` is in view, scrolling as little as possible from
+    /// `start`.
+    pub fn scroll_to(start: usize, selection: usize, rows: usize) -> usize {
+        if selection < start {
+            selection
+        } else if selection >= start + rows {
+            selection + 1 - rows.max(1)
+        } else {
+            start
+        }
+    }
+
+    /// Splits `text` into lines of at most `width` characters, breaking
+    /// between words where it can.
+    pub fn wrap(text: &str, width: usize) -> Vec<String> {
+        let width = width.max(1);
+        let mut lines = vec![];
+        let mut line = String::new();
+        for word in text.split_whitespace() {
+            let mut word: Vec<char> = word.chars().collect();
+            if !line.is_empty() && line.chars().count() + 1 + word.len() > width {
+                lines.push(std::mem::take(&mut line));
+            }
+            while word.len() > width {
+                lines.push(word.drain(..width).collect());
+            }
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line.extend(word);
+        }
+        if !line.is_empty() || lines.is_empty() {
+            lines.push(line);
+        }
+        lines
+    }
+
+    /// The details a repository gave about a level, on one line.
+    fn level_details(meta: &LevelMeta) -> String {
+        let mut details = vec![];
+        if !meta.tags.is_empty() {
+            details.push(format!("Tags: {}", meta.tags.join(", ")));
+        }
+        if let Some(difficulty) = meta.difficulty {
+            details.push(format!("Difficulty: {difficulty}/5"));
+        }
+        if let Some(par) = meta.par {
+            details.push(format!("Par: {par} moves"));
+        }
+        if let Some(size) = meta.size {
+            details.push(format!("Size: {}x{}", size.width, size.height));
+        }
+        if let Some(last_modified) = meta.last_modified {
+            let now = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap()
+                .as_secs();
+            let age = now.saturating_sub(last_modified);
+            let (n, unit) = match age {
+                a if a < 60 * 60 => (a / 60, "minute"),
+                a if a < 60 * 60 * 24 => (a / (60 * 60), "hour"),
+                a if a < 60 * 60 * 24 * 30 => (a / (60 * 60 * 24), "day"),
+                a if a < 60 * 60 * 24 * 365 => (a / (60 * 60 * 24 * 30), "month"),
+                a => (a / (60 * 60 * 24 * 365), "year"),
+            };
+            let plural = if n == 1 { "" } else { "s" };
+            details.push(format!("Updated {n} {unit}{plural} ago"));
+        }
+        details.join(" | ")
+    }
+
+    /// Draws a grid of `num_levels` numbered levels under `title` for the
+    /// player to choose from, where only the levels after the ones in
+    /// `completed_levels` are unlocked. If `names` are given, the name of
+    /// the selected level is shown below the grid.
+    fn level_grid(
+        title: &str,
+        num_levels: usize,
+        completed_levels: &[usize],
+        names: &[&str],
+    ) -> Option<Selection> {
+        let theme = Theme::active();
+        let col_padding = 2;
+        let levels_per_row = (num_levels as f64).sqrt() as u16;
+        let num_rows = (num_levels as f64 / levels_per_row as f64).ceil() as u16;
+        let highest_available_level = match completed_levels.iter().max() {
+            Some(n) => *n.min(&(num_levels - 1)) + 1,
+            None => 0,
+        };
+        let highest_available_level = if highest_available_level >= num_levels {
+            num_levels - 1
+        } else {
+            highest_available_level
+        };
+        let mut current_selection = highest_available_level;
+        loop {
+            let (term_cols, term_rows) = size().unwrap_or((0, 0));
+            let start_row: u16 = (term_rows - num_rows * 2) / 2;
+            let mut start_col: u16 = (term_cols / 2) - levels_per_row * 2;
+            let end_row: u16 = (term_rows + num_rows * 2) / 2;
+            let end_col: u16 = (term_cols / 2) + levels_per_row * 2;
+            #[allow(clippy::manual_is_multiple_of)]
+            if (end_col - start_col + 1) % 2 != 0 {
+                start_col -= 1;
+            }
+            execute!(
+                stdout(),
+                Clear(ClearType::All),
+                MoveTo((term_cols - title.len() as u16) / 2, start_row - 1),
+                Print(theme.highlight().apply(title).bold())
+            )
+            .ok();
+            Menu::draw_borders(start_row, end_row, start_col, end_col).ok();
+            if let Some(name) = names.get(current_selection) {
+                let name: String = name.chars().take(term_cols as usize - 2).collect();
+                execute!(
+                    stdout(),
+                    MoveTo((term_cols - name.len() as u16) / 2, end_row + 1),
+                    SetForegroundColor(theme.text),
+                    Print(name),
+                    ResetColor,
+                )
+                .ok();
+            }
+            for i in 0..num_levels {
+                let is_available = i <= highest_available_level;
+                let style = if is_available || current_selection == i {
+                    theme.item(current_selection == i)
+                } else {
+                    ContentStyle::new()
+                        .with(theme.highlight_text)
+                        .attribute(Attribute::Dim)
+                };
+                execute!(
+                    stdout(),
+                    MoveTo(
+                        (i as u16 % levels_per_row) * 2
+                            + start_col
+                            + col_padding
+                            + (i as u16 % levels_per_row) * 2,
+                        start_row + 1 + (i as u16 / levels_per_row) * 2,
+                    ),
+                    SetStyle(style),
+                    Print(format!("{:0>2}", (i + 1).to_string()).bold()),
+                    ResetColor,
+                )
+                .ok();
+            }
+            let control =
+                Menu::select_clicked(Control::read_input(), &mut current_selection, |c, r| {
+                    (0..=highest_available_level).find(|i| {
+                        let level_col = (*i as u16 % levels_per_row) * 4 + start_col + col_padding;
+                        r == start_row + 1 + (*i as u16 / levels_per_row) * 2
+                            && (level_col..level_col + 2).contains(&c)
+                    })
+                });
+            match control {
+                Control::Up => {
+                    if current_selection == 0 {
+                        current_selection = highest_available_level;
+                    } else if current_selection >= levels_per_row.into() {
+                        current_selection -= levels_per_row as usize;
+                    } else {
+                        current_selection = 0;
+                    }
+                }
+                Control::Down => {
+                    if current_selection == highest_available_level {
+                        current_selection = 0;
+                    } else if current_selection
+                        < (num_levels as i16 - levels_per_row as i16) as usize
+                        && current_selection + (levels_per_row as usize) <= highest_available_level
+                    {
+                        current_selection += levels_per_row as usize;
+                    } else {
+                        current_selection = highest_available_level;
+                    }
+                }
+                Control::Left => {
+                    if current_selection == 0 {
+                        current_selection = highest_available_level;
+                    } else {
+                        current_selection -= 1;
+                    }
+                }
+                Control::Right => {
+                    if current_selection >= highest_available_level {
+                        current_selection = 0;
+                    } else {
+                        current_selection += 1;
+                    }
+                }
+                Control::Quit => return None,
+                Control::Select => return Some(Selection::Item(current_selection)),
+                _ => (),
+            }
+        }
+    }
+
     pub fn open(menu_type: MenuType) -> Option<Selection> {
+        let theme = Theme::active();
         let row_padding = 1;
         let col_padding = 2;
         match menu_type {
-            MenuType::MainSelection(completed_levels) => {
+            MenuType::MainSelection(completed_levels, can_resume) => {
                 let row_padding = 2;
                 let col_padding = 3;
-                let options: [Selection; 4] = [
+                let mut options: Vec<Selection> = vec![
                     Selection::Play(LevelSource::Core(0)),
                     Selection::Repository,
+                    Selection::Profile,
+                    Selection::Settings,
                     Selection::Help,
                     Selection::Quit,
                 ];
+                if can_resume {
+                    options.insert(0, Selection::Resume);
+                }
                 let mut current_selection = 0;
                 loop {
                     let (term_cols, term_rows) = size().unwrap_or((0, 0));
@@ -139,6 +414,7 @@ impl Menu {
                     let end_row: u16 =
                         (term_rows + options.len() as u16 + 10 + row_padding) / 2 + row_padding;
                     let end_col: u16 = (term_cols + 23) / 2 + col_padding;
+                    #[allow(clippy::manual_is_multiple_of)]
                     if (end_col - start_col) % 2 != 0 {
                         start_col -= 1;
                     }
@@ -149,25 +425,25 @@ impl Menu {
                         SetAttribute(Attribute::Bold),
                         MoveTo(start_col + col_padding, start_row + row_padding + 1),
                         Print("          /"),
-                        SetForegroundColor(RED),
+                        SetForegroundColor(theme.laser_on),
                         Print("-------"),
-                        SetBackgroundColor(RED),
+                        SetBackgroundColor(theme.laser_on),
                         Print("L"),
                         SetBackgroundColor(Color::Reset),
                         MoveTo(start_col + col_padding, start_row + row_padding + 2),
-                        SetForegroundColor(Color::Green),
+                        SetForegroundColor(theme.logo),
                         Print(" ___      "),
-                        SetForegroundColor(RED),
+                        SetForegroundColor(theme.laser_on),
                         Print("|"),
-                        SetForegroundColor(Color::Green),
+                        SetForegroundColor(theme.logo),
                         Print("__      _"),
                         MoveTo(start_col + col_padding, start_row + row_padding + 3),
                         Print("|_  |  "),
-                        SetForegroundColor(RED),
+                        SetForegroundColor(theme.laser_on),
                         Print("<--"),
-                        SetForegroundColor(Color::White),
+                        SetForegroundColor(theme.text),
                         Print("/"),
-                        SetForegroundColor(Color::Green),
+                        SetForegroundColor(theme.logo),
                         Print("  |    | \\_"),
                         MoveTo(start_col + col_padding, start_row + row_padding + 4),
                         Print("  | |     `| |    | __|"),
@@ -176,27 +452,27 @@ impl Menu {
                         MoveTo(start_col + col_padding, start_row + row_padding + 6),
                         Print("  | |_    _|_|_   | |_ "),
                         MoveTo(start_col + col_padding, start_row + row_padding + 7),
-                        SetForegroundColor(RED),
+                        SetForegroundColor(theme.laser_on),
                         Print("--"),
-                        SetForegroundColor(Color::White),
+                        SetForegroundColor(theme.text),
                         Print("\\"),
-                        SetForegroundColor(Color::Green),
+                        SetForegroundColor(theme.logo),
                         Print("___\\  |_____| "),
-                        SetForegroundColor(RED),
+                        SetForegroundColor(theme.laser_on),
                         Print("--"),
-                        SetForegroundColor(Color::White),
+                        SetForegroundColor(theme.text),
                         Print("\\"),
-                        SetForegroundColor(Color::Green),
+                        SetForegroundColor(theme.logo),
                         Print("__|"),
                         MoveTo(start_col + col_padding, start_row + row_padding + 8),
-                        SetForegroundColor(RED),
+                        SetForegroundColor(theme.laser_on),
                         Print("  |                v"),
                         MoveTo(start_col + col_padding, start_row + row_padding + 9),
                         Print("  v"),
                         MoveTo(start_col + col_padding, start_row + row_padding + 10),
                         Print("  "),
-                        SetForegroundColor(YELLOW),
-                        SetBackgroundColor(YELLOW),
+                        SetForegroundColor(theme.statue_lit),
+                        SetBackgroundColor(theme.statue_lit),
                         Print("S"),
                         ResetColor,
                     )
@@ -204,23 +480,17 @@ impl Menu {
                     for (i, _) in options.iter().enumerate() {
                         let option = match options[i] {
                             Selection::Play(_) => "P L A Y",
+                            Selection::Resume => "R E S U M E",
                             Selection::Repository => "O N L I N E",
+                            Selection::Profile => "P R O F I L E",
+                            Selection::Settings => "S E T T I N G S",
                             Selection::Help => "H E L P",
                             Selection::Quit => "Q U I T",
                             _ => "",
                         };
                         execute!(
                             stdout(),
-                            SetForegroundColor(if i == current_selection {
-                                Color::Black
-                            } else {
-                                Color::White
-                            }),
-                            SetBackgroundColor(if i == current_selection {
-                                Color::White
-                            } else {
-                                Color::Reset
-                            }),
+                            SetStyle(theme.item(i == current_selection)),
                             MoveTo(
                                 (term_cols - 23) / 2,
                                 start_row + row_padding * 2 + i as u16 * 2 + 10,
@@ -231,7 +501,18 @@ impl Menu {
                         )
                         .ok();
                     }
-                    match Control::read_input() {
+                    let control = Menu::select_clicked(
+                        Control::read_input(),
+                        &mut current_selection,
+                        |c, r| {
+                            let option_col = (term_cols - 23) / 2;
+                            (0..options.len()).find(|i| {
+                                r == start_row + row_padding * 2 + *i as u16 * 2 + 10
+                                    && (option_col..option_col + 23).contains(&c)
+                            })
+                        },
+                    );
+                    match control {
                         Control::Select => match options[current_selection] {
                             Selection::Play(_) => {
                                 if let Some(Selection::Item(i)) =
@@ -260,18 +541,27 @@ impl Menu {
             }
             MenuType::Message(message) => loop {
                 let (term_cols, term_rows) = size().unwrap_or((0, 0));
-                let start_row: u16 = term_rows / 2 - row_padding - 1;
+                // Long messages, like errors with a URL in them, are wrapped
+                // to fit between the borders, and cut off if they still
+                // don't fit on the screen.
+                let mut lines = Menu::wrap(message, term_cols.saturating_sub(6) as usize);
+                lines.truncate(term_rows.saturating_sub(4).max(1) as usize);
+                let width = lines.iter().map(|l| l.chars().count()).max().unwrap_or(0) as u16;
+                let first_row = (term_rows / 2).saturating_sub(lines.len() as u16 / 2);
+                let start_row: u16 = first_row.saturating_sub(row_padding + 1);
                 let start_col: u16 =
-                    (term_cols - (term_cols - 4).min(message.len() as u16)) / 2 - col_padding;
-                let end_row: u16 = (term_rows + row_padding) / 2 + row_padding;
-                let end_col: u16 = (term_cols + message.len() as u16) / 2 + col_padding;
+                    (term_cols.saturating_sub(width) / 2).saturating_sub(col_padding);
+                let end_row: u16 = first_row + lines.len() as u16 - 1 + row_padding;
+                let end_col: u16 = start_col + width + 2 * col_padding;
                 Menu::draw_borders(start_row, end_row, start_col, end_col).ok();
-                execute!(
-                    stdout(),
-                    MoveTo(start_col + 2, term_rows / 2),
-                    Print(message),
-                )
-                .ok();
+                for (i, line) in lines.iter().enumerate() {
+                    execute!(
+                        stdout(),
+                        MoveTo(start_col + col_padding, first_row + i as u16),
+                        Print(line),
+                    )
+                    .ok();
+                }
                 if let Control::Select = Control::read_input() {
                     break;
                 }
@@ -294,28 +584,10 @@ impl Menu {
                     execute!(
                         stdout(),
                         MoveTo(term_cols / 2 - 6, end_row - row_padding - 1),
-                        SetForegroundColor(if matches!(current_selection, Selection::Yes) {
-                            Color::Black
-                        } else {
-                            Color::White
-                        }),
-                        SetBackgroundColor(if matches!(current_selection, Selection::Yes) {
-                            Color::White
-                        } else {
-                            Color::Reset
-                        }),
+                        SetStyle(theme.item(matches!(current_selection, Selection::Yes))),
                         Print(" YES ".bold()),
                         MoveTo(term_cols / 2 + 1, end_row - row_padding - 1),
-                        SetForegroundColor(if matches!(current_selection, Selection::No) {
-                            Color::Black
-                        } else {
-                            Color::White
-                        }),
-                        SetBackgroundColor(if matches!(current_selection, Selection::No) {
-                            Color::White
-                        } else {
-                            Color::Reset
-                        }),
+                        SetStyle(theme.item(matches!(current_selection, Selection::No))),
                         Print(" NO ".bold())
                     )
                     .ok();
@@ -333,42 +605,264 @@ impl Menu {
                     }
                 }
             }
+            MenuType::Selection(message, options) => {
+                if options.is_empty() {
+                    return None;
+                }
+                let message = format!(" {message} ");
+                let option_len = options.iter().map(|o| o.len()).max().unwrap_or(0);
+                let mut current_selection = 0;
+                loop {
+                    let (term_cols, term_rows) = size().unwrap_or((0, 0));
+                    let num_cols = (option_len.max(message.len()) as u16 + 4).min(term_cols - 4);
+                    let option_len = num_cols as usize - 3;
+                    let start_row: u16 = (term_rows - options.len() as u16) / 2;
+                    let start_col: u16 = (term_cols - num_cols) / 2;
+                    let end_row: u16 = (term_rows + options.len() as u16) / 2 + 1;
+                    let end_col: u16 = (term_cols + num_cols) / 2;
+                    execute!(
+                        stdout(),
+                        Clear(ClearType::All),
+                        MoveTo((term_cols - message.len() as u16) / 2, start_row - 1),
+                        Print(theme.highlight().apply(message.as_str()).bold())
+                    )
+                    .ok();
+                    Menu::draw_borders(start_row, end_row, start_col, end_col).ok();
+                    for (i, option) in options.iter().enumerate() {
+                        execute!(
+                            stdout(),
+                            SetStyle(theme.item(i == current_selection)),
+                            MoveTo(start_col + 1, start_row + i as u16 + 1),
+                            Print(
+                                format!(" {: <option_len$}", Menu::truncate(option, option_len))
+                                    .bold()
+                            ),
+                            ResetColor
+                        )
+                        .ok();
+                    }
+                    let control = Menu::select_clicked(
+                        Control::read_input(),
+                        &mut current_selection,
+                        |c, r| {
+                            (0..options.len()).find(|i| {
+                                r == start_row + *i as u16 + 1 && c > start_col && c < end_col
+                            })
+                        },
+                    );
+                    match control {
+                        Control::Up | Control::ScrollUp => {
+                            if current_selection == 0 {
+                                current_selection = options.len() - 1;
+                            } else {
+                                current_selection -= 1;
+                            }
+                        }
+                        Control::Down | Control::ScrollDown => {
+                            current_selection = (current_selection + 1) % options.len();
+                        }
+                        Control::Select => return Some(Selection::Item(current_selection)),
+                        Control::Quit => return None,
+                        _ => (),
+                    }
+                }
+            }
+            MenuType::TextInput(message, initial) => {
+                let message = format!(" {message} ");
+                let hint = " ENTER TO CONFIRM, ESC TO CANCEL ";
+                let mut text = initial;
+                loop {
+                    let (term_cols, term_rows) = size().unwrap_or((0, 0));
+                    let num_cols = (term_cols - 4).min(60);
+                    let start_row: u16 = term_rows / 2 - row_padding - 1;
+                    let start_col: u16 = (term_cols - num_cols) / 2;
+                    let end_row: u16 = term_rows / 2 + row_padding + 1;
+                    let end_col: u16 = (term_cols + num_cols) / 2;
+                    let chars: Vec<char> = text.chars().collect();
+                    let visible = num_cols as usize - col_padding as usize * 2 - 2;
+                    let shown: String = chars[chars.len().saturating_sub(visible)..]
+                        .iter()
+                        .collect();
+                    execute!(
+                        stdout(),
+                        Clear(ClearType::All),
+                        MoveTo((term_cols - message.len() as u16) / 2, start_row - 1),
+                        Print(theme.highlight().apply(message.as_str()).bold()),
+                        MoveTo((term_cols - hint.len() as u16) / 2, end_row + 1),
+                        Print(theme.highlight().apply(hint).bold()),
+                    )
+                    .ok();
+                    Menu::draw_borders(start_row, end_row, start_col, end_col).ok();
+                    execute!(
+                        stdout(),
+                        MoveTo(start_col + col_padding, term_rows / 2),
+                        Print(shown),
+                        Print(theme.highlight().apply(" ")),
+                    )
+                    .ok();
+                    match TextInput::read_input() {
+                        TextInput::Char(c) => text.push(c),
+                        TextInput::Backspace => {
+                            text.pop();
+                        }
+                        TextInput::Submit => return Some(Selection::Text(text)),
+                        TextInput::Cancel => return None,
+                        TextInput::None => (),
+                    }
+                }
+            }
+            MenuType::Settings(settings, themes) => {
+                let message = " SETTINGS ";
+                let keymap = Keymap::active();
+                let hint = format!(
+                    " {} / {} TO CHANGE, {} TO GO BACK ",
+                    keymap.describe(Control::Left),
+                    keymap.describe(Control::Right),
+                    keymap.describe(Control::Quit)
+                );
+                let num_options = 5;
+                let mut current_selection = 0;
+                loop {
+                    let on_off = |b: bool| if b { "ON" } else { "OFF" }.to_string();
+                    let options = [
+                        (
+                            "Animation Delay",
+                            format!("{} ms", settings.animation_delay),
+                        ),
+                        ("Confirm On Quit", on_off(settings.confirm_on_quit)),
+                        ("Theme", settings.theme.to_uppercase()),
+                        ("ASCII Only", on_off(settings.ascii_only)),
+                        ("Auto Advance", on_off(settings.auto_advance)),
+                    ];
+                    let (term_cols, term_rows) = size().unwrap_or((0, 0));
+                    let num_cols = 40.min(term_cols - 4);
+                    let option_len = num_cols as usize - 3;
+                    let start_row: u16 = (term_rows - num_options as u16) / 2;
+                    let start_col: u16 = (term_cols - num_cols) / 2;
+                    let end_row: u16 = (term_rows + num_options as u16) / 2 + 1;
+                    let end_col: u16 = (term_cols + num_cols) / 2;
+                    execute!(
+                        stdout(),
+                        Clear(ClearType::All),
+                        MoveTo((term_cols - message.len() as u16) / 2, start_row - 1),
+                        Print(theme.highlight().apply(message).bold()),
+                        MoveTo((term_cols - hint.len() as u16) / 2, end_row + 1),
+                        Print(theme.highlight().apply(hint.as_str()).bold()),
+                    )
+                    .ok();
+                    Menu::draw_borders(start_row, end_row, start_col, end_col).ok();
+                    for (i, (name, value)) in options.iter().enumerate() {
+                        let value_len = option_len.saturating_sub(name.len() + 1);
+                        execute!(
+                            stdout(),
+                            SetStyle(theme.item(i == current_selection)),
+                            MoveTo(start_col + 1, start_row + i as u16 + 1),
+                            Print(format!(" {name}{value: >value_len$} ").bold()),
+                            ResetColor
+                        )
+                        .ok();
+                    }
+                    let control = Menu::select_clicked(
+                        Control::read_input(),
+                        &mut current_selection,
+                        |c, r| {
+                            (0..num_options).find(|i| {
+                                r == start_row + *i as u16 + 1 && c > start_col && c < end_col
+                            })
+                        },
+                    );
+                    let step: i64 = match control {
+                        Control::Up | Control::ScrollUp => {
+                            if current_selection == 0 {
+                                current_selection = num_options - 1;
+                            } else {
+                                current_selection -= 1;
+                            }
+                            continue;
+                        }
+                        Control::Down | Control::ScrollDown => {
+                            current_selection = (current_selection + 1) % num_options;
+                            continue;
+                        }
+                        Control::Left => -1,
+                        Control::Right | Control::Select => 1,
+                        Control::Quit => break,
+                        _ => continue,
+                    };
+                    match current_selection {
+                        0 => {
+                            settings.animation_delay = (settings.animation_delay as i64
+                                + step * 100)
+                                .clamp(0, Settings::MAX_ANIMATION_DELAY as i64)
+                                as u64
+                        }
+                        1 => settings.confirm_on_quit = !settings.confirm_on_quit,
+                        2 => {
+                            let current = themes
+                                .iter()
+                                .position(|t| *t == settings.theme)
+                                .unwrap_or(0) as i64;
+                            settings.theme = themes
+                                [(current + step).rem_euclid(themes.len() as i64) as usize]
+                                .to_string();
+                        }
+                        3 => settings.ascii_only = !settings.ascii_only,
+                        4 => settings.auto_advance = !settings.auto_advance,
+                        _ => (),
+                    }
+                }
+            }
             MenuType::HelpMenu => {
-                return Menu::open(MenuType::ScrollableMenu(vec![
+                let keymap = Keymap::active();
+                let controls: Vec<(String, &str)> = Control::BINDABLE
+                    .iter()
+                    .map(|c| (format!(" {} - ", keymap.describe(*c)), c.description()))
+                    .collect();
+                let action_keys = keymap.describe(Control::Action);
+                let mut help = vec![
                     vec![],
                     vec![
                         "In ".stylize(),
-                        "l1t".bold().green(),
+                        "l1t".bold().with(theme.logo),
                         ", your goal is to use the available lasers ".stylize(),
                     ],
                     vec!["to light up all of the statues in the level.".stylize()],
                     vec![],
                     vec!["CONTROLS".bold().underlined()],
                     vec![],
-                    vec![" W - ".bold(), "Move Up".stylize()],
-                    vec![" S - ".bold(), "Move Down".stylize()],
-                    vec![" A - ".bold(), "Move Left".stylize()],
-                    vec![" D - ".bold(), "Move Right".stylize()],
+                ];
+                for (keys, description) in controls.iter() {
+                    help.push(vec![keys.as_str().bold(), description.stylize()]);
+                }
+                help.append(&mut vec![
+                    vec![],
+                    vec!["Controls can be changed in your `keys.toml`".stylize()],
+                    vec!["file in `$XDG_CONFIG_HOME/l1t`.".stylize()],
+                    vec![],
+                    vec!["In some levels, you must press a direction after".stylize()],
                     vec![
-                        " Space - ".bold(),
-                        "Toggle surrounding blocks (if able)".stylize(),
+                        action_keys.as_str().bold(),
+                        " to choose the one block to toggle.".stylize(),
                     ],
-                    vec![" Shift-H - ".bold(), "Show this help menu".stylize()],
-                    vec![" Q - ".bold(), "Quit".stylize()],
                     vec![],
-                    vec!["Arrow keys can also be used to move around the ".stylize()],
-                    vec!["level".stylize()],
+                    vec!["You can also click menu items, scroll with the".stylize()],
+                    vec!["mouse wheel and click a block next to you to".stylize()],
+                    vec!["toggle only that block.".stylize()],
                     vec![],
                     vec![
-                        "X".green().on_green(),
+                        "X".with(theme.player).on(theme.player),
                         " ".stylize(),
                         "PLAYER".bold().underlined(),
                     ],
                     vec![],
                     vec!["Hey, that's you!".stylize()],
                     vec![],
+                    vec!["The arrow on the player shows which way you're".stylize()],
+                    vec!["facing. Pulling steps you backwards and drags".stylize()],
+                    vec!["the moveable block you're facing along with you.".stylize()],
+                    vec![],
                     vec![
-                        "L".with(RED).on(RED),
+                        "L".with(theme.laser_on).on(theme.laser_on),
                         " ".stylize(),
                         "LASERS".bold().underlined(),
                     ],
@@ -387,7 +881,7 @@ impl Menu {
                     vec![],
                     vec![
                         "If a laser hits you, you'll ".stylize(),
-                        "die".with(RED).bold(),
+                        "die".with(theme.player_dead).bold(),
                         " and have to ".stylize(),
                     ],
                     vec!["restart the level.".stylize()],
@@ -396,7 +890,7 @@ impl Menu {
                     vec!["turn off and must be toggled on by the player.".stylize()],
                     vec![],
                     vec![
-                        "S".with(YELLOW).on(YELLOW),
+                        "S".with(theme.statue_lit).on(theme.statue_lit),
                         " ".stylize(),
                         "STATUES".bold().underlined(),
                     ],
@@ -404,14 +898,14 @@ impl Menu {
                     vec!["All statues in a level must be lit up by a ".stylize()],
                     vec![
                         "laser beam to ".stylize(),
-                        "win".with(YELLOW).bold(),
+                        "win".with(theme.statue_lit).bold(),
                         " the level.".stylize(),
                     ],
                     vec![],
                     vec!["Statues can not be moved or manually toggled.".stylize()],
                     vec![],
                     vec![
-                        "R".bold().black().on(YELLOW),
+                        "R".bold().with(theme.contrast).on(theme.statue_lit),
                         " ".stylize(),
                         "REVERSE STATUES".bold().underlined(),
                     ],
@@ -423,7 +917,7 @@ impl Menu {
                     ],
                     vec![
                         "to ".stylize(),
-                        "win".with(YELLOW).bold(),
+                        "win".with(theme.statue_lit).bold(),
                         " the level.".stylize(),
                     ],
                     vec![],
@@ -432,23 +926,26 @@ impl Menu {
                     vec!["Mirrors reflect laser beams in different".stylize()],
                     vec!["directions.".stylize()],
                     vec![],
-                    vec!["             ".stylize(), "L".with(RED).on(RED)],
-                    vec!["             |".bold().with(RED)],
                     vec![
-                        "L".with(RED).on(RED),
-                        "----".bold().with(RED),
+                        "             ".stylize(),
+                        "L".with(theme.laser_on).on(theme.laser_on),
+                    ],
+                    vec!["             |".bold().with(theme.beam)],
+                    vec![
+                        "L".with(theme.laser_on).on(theme.laser_on),
+                        "----".bold().with(theme.beam),
                         "\\".bold(),
-                        "    <--".bold().with(RED),
+                        "    <--".bold().with(theme.beam),
                         "/".bold(),
                     ],
-                    vec!["     |".with(RED).bold()],
-                    vec!["     V".with(RED).bold()],
+                    vec!["     |".with(theme.beam).bold()],
+                    vec!["     V".with(theme.beam).bold()],
                     vec![],
                     vec!["Mirrors cannot be moved but their direction can ".stylize()],
                     vec!["be toggled by the player.".stylize()],
                     vec![],
                     vec![
-                        "/".black().on_white().bold(),
+                        "/".with(theme.contrast).on(theme.moveable_mirror).bold(),
                         " ".stylize(),
                         "MOVEABLE MIRRORS".bold().underlined(),
                     ],
@@ -461,7 +958,7 @@ impl Menu {
                     ],
                     vec![],
                     vec![
-                        "Z".bold().yellow().on_black(),
+                        "Z".bold().with(theme.zapper),
                         " ".stylize(),
                         "ZAPPERS".bold().underlined(),
                     ],
@@ -469,36 +966,36 @@ impl Menu {
                     vec!["If any Zappers are lit by a laser beam, you".stylize()],
                     vec![
                         "will immediately ".stylize(),
-                        "lose".with(RED).bold(),
+                        "lose".with(theme.player_dead).bold(),
                         " the level.".stylize(),
                     ],
                     vec![],
                     vec![
-                        "I".bold().white().on_white(),
+                        "I".bold().with(theme.wall).on(theme.wall),
                         " ".stylize(),
-                        "B".bold().grey().on_grey(),
+                        "B".bold().with(theme.block).on(theme.block),
                         " ".stylize(),
-                        "s".bold().black().on_red(),
+                        "s".bold().with(theme.contrast).on(theme.off),
                         " ".stylize(),
                         "OTHER BLOCKS".bold().underlined(),
                     ],
                     vec![],
                     vec![
-                        "I".bold().white().on_white(),
+                        "I".bold().with(theme.wall).on(theme.wall),
                         " Walls - ".bold(),
                         "Cannot be moved by player, will block".stylize(),
                     ],
                     vec!["          laser beams.".stylize()],
                     vec![],
                     vec![
-                        "B".bold().grey().on_grey(),
+                        "B".bold().with(theme.block).on(theme.block),
                         " Blocks - ".bold(),
                         "Can be moved around and will block".stylize(),
                     ],
                     vec!["           laser beams.".stylize()],
                     vec![],
                     vec![
-                        "T".bold().magenta().on_magenta(),
+                        "T".bold().with(theme.toggle_block).on(theme.toggle_block),
                         " Toggle Blocks - ".bold(),
                         "Cannot be moved. Switches and".stylize(),
                     ],
@@ -506,14 +1003,14 @@ impl Menu {
                     vec!["                  and off.".stylize()],
                     vec![],
                     vec![
-                        "s".bold().black().on_red(),
+                        "s".bold().with(theme.contrast).on(theme.off),
                         " Switches - ".bold(),
                         "When toggled, will turn toggle".stylize(),
                     ],
                     vec!["             blocks on/off.".stylize()],
                     vec![],
                     vec![
-                        "b".bold().black().on_red(),
+                        "b".bold().with(theme.contrast).on(theme.off),
                         " Buttons - ".bold(),
                         "When pressed, will turn toggle".stylize(),
                     ],
@@ -523,24 +1020,45 @@ impl Menu {
                     vec!["REPOSITORIES".bold().underlined()],
                     vec![],
                     vec!["Repositories allow you to play levels hosted ".stylize()],
-                    vec!["online. To add a repository, add a line in ".stylize()],
-                    vec!["your `$HOME/.l1t/repositories.l1t_conf` file:".stylize()],
+                    vec!["online. Repositories can be added, edited,".stylize()],
+                    vec!["removed and reordered from the ONLINE menu".stylize()],
+                    vec!["with the controls above, or in your".stylize()],
+                    vec!["`repositories.toml` file in".stylize()],
+                    vec!["`$XDG_CONFIG_HOME/l1t`:".stylize()],
+                    vec![],
+                    vec!["[[repository]]".stylize()],
+                    vec!["name = \"My Repo Name\"".stylize()],
+                    vec!["url = \"http://myrepourl.com\"".stylize()],
                     vec![],
-                    vec!["My Repo Name = http://myrepourl.com".stylize()],
+                    vec!["Each repository can also be disabled or".stylize()],
+                    vec!["given TLS, auth, caching and sorting options,".stylize()],
+                    vec!["see the repo documentation.".stylize()],
                     vec![],
-                    vec!["The left side of the `=` is the repository ".stylize()],
-                    vec!["name and the right is the URL of the repo.".stylize()],
+                    vec!["A repository's URL can also be a folder on".stylize()],
+                    vec!["your computer, like `~/levels`, or a level".stylize()],
+                    vec!["pack, which is played in order like the".stylize()],
+                    vec!["core levels.".stylize()],
                     vec![],
                     vec!["To host your own repo, checkout the repo ".stylize()],
                     vec!["documentation at:".stylize()],
                     vec![],
                     vec!["https://github.com/alex-laycalvert/l1t/".stylize()],
-                ]))
+                ]);
+                return Menu::open(MenuType::ScrollableMenu(help));
             }
             MenuType::ScrollableMenu(content) => {
                 let mut start_index: usize = 0;
-                let scroll_message = "  USE ARROW KEYS OR W, S TO SCROLL  ";
-                let fast_scroll_message = "  USE g AND G to GOTO TOP AND BOTTOM  ";
+                let keymap = Keymap::active();
+                let scroll_message = format!(
+                    "  USE {} / {} TO SCROLL  ",
+                    keymap.describe(Control::Up),
+                    keymap.describe(Control::Down)
+                );
+                let fast_scroll_message = format!(
+                    "  USE {} AND {} TO GOTO TOP AND BOTTOM  ",
+                    keymap.describe(Control::GotoTop),
+                    keymap.describe(Control::GotoBottom)
+                );
                 loop {
                     let (term_cols, term_rows) = size().unwrap_or((0, 0));
                     let lines: usize = (term_rows - row_padding * 2) as usize - 6;
@@ -552,12 +1070,12 @@ impl Menu {
                         stdout(),
                         Clear(ClearType::All),
                         MoveTo((term_cols - scroll_message.len() as u16) / 2, start_row - 1),
-                        Print(scroll_message.on_white().black().bold()),
+                        Print(theme.highlight().apply(scroll_message.as_str()).bold()),
                         MoveTo(
                             (term_cols - fast_scroll_message.len() as u16) / 2,
                             end_row + 1
                         ),
-                        Print(fast_scroll_message.on_white().black().bold())
+                        Print(theme.highlight().apply(fast_scroll_message.as_str()).bold())
                     )
                     .ok();
                     Menu::draw_borders(start_row, end_row, start_col, end_col).ok();
@@ -580,13 +1098,13 @@ impl Menu {
                         }
                     }
                     match Control::read_input() {
-                        Control::Up => {
+                        Control::Up | Control::ScrollUp => {
                             if start_index == 0 {
                                 continue;
                             }
                             start_index -= 1;
                         }
-                        Control::Down => {
+                        Control::Down | Control::ScrollDown => {
                             if start_index + lines >= content.len() {
                                 continue;
                             }
@@ -600,145 +1118,81 @@ impl Menu {
                 }
             }
             MenuType::CoreLevelSelection(completed_levels) => {
-                let num_levels = Level::NUM_CORE_LEVELS as f64;
-                let levels_per_row = num_levels.sqrt() as u16;
-                let num_rows = (num_levels / levels_per_row as f64).ceil() as u16;
-                let highest_available_level = match completed_levels.iter().max() {
-                    Some(n) => *n.min(&(Level::NUM_CORE_LEVELS - 1)) + 1,
-                    None => 0,
-                };
-                let highest_available_level = if highest_available_level >= Level::NUM_CORE_LEVELS {
-                    Level::NUM_CORE_LEVELS - 1
-                } else {
-                    highest_available_level
-                };
-                let mut current_selection = highest_available_level;
-                let message = "  SELECT A LEVEL  ";
-                loop {
-                    let (term_cols, term_rows) = size().unwrap_or((0, 0));
-                    let start_row: u16 = (term_rows - num_rows * 2) / 2;
-                    let mut start_col: u16 = (term_cols / 2) - levels_per_row * 2;
-                    let end_row: u16 = (term_rows + num_rows * 2) / 2;
-                    let end_col: u16 = (term_cols / 2) + levels_per_row * 2;
-                    if (end_col - start_col + 1) % 2 != 0 {
-                        start_col -= 1;
-                    }
-                    execute!(
-                        stdout(),
-                        Clear(ClearType::All),
-                        MoveTo((term_cols - message.len() as u16) / 2, start_row - 1),
-                        Print(message.on_white().black().bold())
-                    )
-                    .ok();
-                    Menu::draw_borders(start_row, end_row, start_col, end_col).ok();
-                    for i in 0..Level::NUM_CORE_LEVELS {
-                        let is_available = i <= highest_available_level;
-                        let fg_color = if is_available && current_selection != i {
-                            Color::White
-                        } else {
-                            Color::Black
-                        };
-                        execute!(
-                            stdout(),
-                            MoveTo(
-                                (i as u16 % levels_per_row) * 2
-                                    + start_col
-                                    + col_padding
-                                    + (i as u16 % levels_per_row) * 2,
-                                start_row + 1 + (i as u16 / levels_per_row) * 2,
-                            ),
-                            SetForegroundColor(fg_color),
-                            SetBackgroundColor(if current_selection == i {
-                                Color::White
-                            } else {
-                                Color::Reset
-                            }),
-                            Print(format!("{:0>2}", (i + 1).to_string()).bold()),
-                        )
-                        .ok();
-                    }
-                    match Control::read_input() {
-                        Control::Up => {
-                            if current_selection == 0 {
-                                current_selection = highest_available_level;
-                            } else if current_selection >= levels_per_row.into() {
-                                current_selection -= levels_per_row as usize;
-                            } else {
-                                current_selection = 0;
-                            }
-                        }
-                        Control::Down => {
-                            if current_selection == highest_available_level {
-                                current_selection = 0;
-                            } else if current_selection
-                                < (Level::NUM_CORE_LEVELS as i16 - levels_per_row as i16) as usize
-                                && current_selection + (levels_per_row as usize)
-                                    <= highest_available_level
-                            {
-                                current_selection += levels_per_row as usize;
-                            } else {
-                                current_selection = highest_available_level;
-                            }
-                        }
-                        Control::Left => {
-                            if current_selection == 0 {
-                                current_selection = highest_available_level;
-                            } else {
-                                current_selection -= 1;
-                            }
-                        }
-                        Control::Right => {
-                            if current_selection >= highest_available_level {
-                                current_selection = 0;
-                            } else {
-                                current_selection += 1;
-                            }
-                        }
-                        Control::Quit => return None,
-                        Control::Select => return Some(Selection::Item(current_selection)),
-                        _ => (),
-                    }
-                }
+                return Menu::level_grid(
+                    "  SELECT A LEVEL  ",
+                    Level::NUM_CORE_LEVELS,
+                    completed_levels,
+                    &[],
+                );
             }
-            MenuType::RepositorySelection(repositories) => {
+            MenuType::PackLevelSelection(pack, completed_levels) => {
+                let title = format!("  {}  ", pack.name.to_uppercase());
+                let names: Vec<&str> = pack.levels.iter().map(|l| l.name.as_str()).collect();
+                return Menu::level_grid(&title, pack.levels.len(), completed_levels, &names);
+            }
+            MenuType::RepositorySelection(repositories, selected) => {
                 let message = " SELECT A REPO ";
-                let mut current_selection = 0;
+                let keymap = Keymap::active();
+                let hint = format!(
+                    " {} NEW, {} EDIT, {} DELETE, {} / {} MOVE, {} DOWNLOAD ",
+                    keymap.describe(Control::New),
+                    keymap.describe(Control::Edit),
+                    keymap.describe(Control::Delete),
+                    keymap.describe(Control::MoveUp),
+                    keymap.describe(Control::MoveDown),
+                    keymap.describe(Control::Download)
+                );
+                let empty = format!(
+                    "No repositories yet, press {} to add one.",
+                    keymap.describe(Control::New)
+                );
+                let num_rows = repositories.len().max(1);
+                let mut current_selection = selected.min(num_rows - 1);
                 loop {
                     let (term_cols, term_rows) = size().unwrap_or((0, 0));
                     let num_cols = (term_cols - 4).min(200) as usize;
                     let repo_name_len = num_cols / 2 - 2;
                     let repo_url_len = num_cols - 4 - repo_name_len;
-                    let start_row: u16 = (term_rows - repositories.len() as u16) / 2;
+                    let start_row: u16 = (term_rows - num_rows as u16) / 2;
                     let start_col: u16 = (term_cols - num_cols as u16) / 2;
-                    let end_row: u16 = (term_rows + repositories.len() as u16) / 2 + 1;
+                    let end_row: u16 = (term_rows + num_rows as u16) / 2 + 1;
                     let end_col: u16 = (term_cols + num_cols as u16) / 2;
                     execute!(
                         stdout(),
                         Clear(ClearType::All),
                         MoveTo((term_cols - message.len() as u16) / 2, start_row - 1),
-                        Print(message.on_white().black().bold())
+                        Print(theme.highlight().apply(message).bold()),
+                        MoveTo(
+                            (term_cols.saturating_sub(hint.len() as u16)) / 2,
+                            end_row + 1
+                        ),
+                        Print(theme.highlight().apply(hint.as_str()).bold()),
                     )
                     .ok();
                     Menu::draw_borders(start_row, end_row, start_col, end_col).ok();
+                    if repositories.is_empty() {
+                        execute!(
+                            stdout(),
+                            MoveTo(start_col + 2, start_row + 1),
+                            Print(&empty[0..empty.len().min(num_cols - 4)]),
+                        )
+                        .ok();
+                    }
                     for (i, repo) in repositories.iter().enumerate() {
+                        let name = if repo.enabled {
+                            repo.name.to_string()
+                        } else {
+                            format!("{} (disabled)", repo.name)
+                        };
                         execute!(
                             stdout(),
-                            SetBackgroundColor(if i == current_selection {
-                                Color::White
-                            } else {
-                                Color::Reset
-                            }),
-                            SetForegroundColor(if i == current_selection {
-                                Color::Black
-                            } else {
-                                Color::White
-                            }),
+                            SetStyle(theme.item(i == current_selection)),
                             MoveTo(start_col + 1, start_row + i as u16 + 1),
                             Print(
                                 format!(
                                     " {: <repo_name_len$} {: <repo_url_len$} ",
-                                    &repo.name[0..repo.name.len().min(repo_name_len)],
-                                    &repo.url[0..repo.url.len().min(repo_url_len)],
+                                    Menu::truncate(&name, repo_name_len),
+                                    Menu::truncate(&repo.url, repo_url_len),
                                 )
                                 .bold()
                             ),
@@ -746,15 +1200,27 @@ impl Menu {
                         )
                         .ok();
                     }
-                    match Control::read_input() {
-                        Control::Up => {
+                    let control = Menu::select_clicked(
+                        Control::read_input(),
+                        &mut current_selection,
+                        |c, r| {
+                            (0..repositories.len()).find(|i| {
+                                r == start_row + *i as u16 + 1 && c > start_col && c < end_col
+                            })
+                        },
+                    );
+                    match control {
+                        Control::New => return Some(Selection::New),
+                        Control::Quit => break,
+                        _ if repositories.is_empty() => continue,
+                        Control::Up | Control::ScrollUp => {
                             if current_selection == 0 {
                                 current_selection = repositories.len() - 1;
                             } else {
                                 current_selection -= 1;
                             }
                         }
-                        Control::Down => {
+                        Control::Down | Control::ScrollDown => {
                             if current_selection == repositories.len() - 1 {
                                 current_selection = 0;
                             } else {
@@ -762,57 +1228,121 @@ impl Menu {
                             }
                         }
                         Control::Select => return Some(Selection::Item(current_selection)),
-                        Control::None => continue,
-                        _ => break,
+                        Control::Edit => return Some(Selection::Edit(current_selection)),
+                        Control::Delete => return Some(Selection::Delete(current_selection)),
+                        Control::MoveUp => return Some(Selection::MoveUp(current_selection)),
+                        Control::MoveDown => return Some(Selection::MoveDown(current_selection)),
+                        Control::Download => return Some(Selection::Download(current_selection)),
+                        _ => continue,
                     }
                 }
             }
-            MenuType::RepositoryLevelSelection(repository, completed_levels) => {
-                let message = " SELECT A REPO ";
+            MenuType::RepositoryLevelSelection(repository, completed_levels, search) => {
+                let message = match (repository.stale, search.is_empty()) {
+                    (false, true) => " SELECT A LEVEL ".to_string(),
+                    (false, false) => format!(" SEARCH: {search} "),
+                    (true, true) => " SELECT A LEVEL (OFFLINE, MAY BE OUT OF DATE) ".to_string(),
+                    (true, false) => format!(" SEARCH: {search} (OFFLINE, MAY BE OUT OF DATE) "),
+                };
+                let keymap = Keymap::active();
+                let hint = format!(
+                    " {} TO SEARCH, {} TO GO BACK ",
+                    keymap.describe(Control::Search),
+                    keymap.describe(Control::Quit)
+                );
                 let mut current_selection = 0;
+                let mut start_index = 0;
                 loop {
                     let (term_cols, term_rows) = size().unwrap_or((0, 0));
-                    let num_cols = (term_cols - 4).min(200) as usize;
-                    let level_name_len = num_cols / 5 - 2;
+                    let num_cols = term_cols.saturating_sub(4).min(200) as usize;
+                    let level_name_len = (num_cols / 5).saturating_sub(2);
                     let level_author_len = level_name_len;
-                    let level_desc_len = num_cols - level_name_len - level_author_len - 6;
-                    let start_row: u16 = (term_rows - repository.levels.len() as u16) / 2;
-                    let start_col: u16 = (term_cols - num_cols as u16) / 2;
-                    let end_row: u16 = (term_rows + repository.levels.len() as u16) / 2 + 1;
+                    let level_desc_len =
+                        num_cols.saturating_sub(level_name_len + level_author_len + 6);
+                    // Listings can be taller than the terminal, so only the
+                    // rows around the selection that fit between the title
+                    // and the details are drawn.
+                    let num_rows = repository
+                        .levels
+                        .len()
+                        .clamp(1, term_rows.saturating_sub(8).max(1) as usize);
+                    start_index = Menu::scroll_to(start_index, current_selection, num_rows)
+                        .min(repository.levels.len().saturating_sub(num_rows));
+                    let start_row: u16 = term_rows.saturating_sub(num_rows as u16) / 2;
+                    let start_col: u16 = term_cols.saturating_sub(num_cols as u16) / 2;
+                    let end_row: u16 = (term_rows + num_rows as u16) / 2 + 1;
                     let end_col: u16 = (term_cols + num_cols as u16) / 2;
+                    let details = repository
+                        .levels
+                        .get(current_selection)
+                        .map(|level| {
+                            let mut details = Menu::level_details(&level.meta);
+                            let commit = level.source.url().and_then(|url| {
+                                completed_levels
+                                    .iter()
+                                    .find(|l| l.matches(&url, level.meta.hash.as_deref()))
+                                    .and_then(|l| l.commit.as_ref())
+                            });
+                            if let Some(commit) = commit {
+                                if !details.is_empty() {
+                                    details += " | ";
+                                }
+                                details +=
+                                    &format!("Completed at commit {}", Menu::truncate(commit, 7));
+                            }
+                            details
+                        })
+                        .unwrap_or_default();
+                    let details = Menu::truncate(&details, num_cols);
                     execute!(
                         stdout(),
                         Clear(ClearType::All),
-                        MoveTo((term_cols - message.len() as u16) / 2, start_row - 1),
-                        Print(message.on_white().black().bold())
+                        MoveTo(
+                            term_cols.saturating_sub(message.len() as u16) / 2,
+                            start_row.saturating_sub(1)
+                        ),
+                        Print(theme.highlight().apply(message.as_str()).bold()),
+                        MoveTo(
+                            term_cols.saturating_sub(details.chars().count() as u16) / 2,
+                            end_row + 1
+                        ),
+                        Print(details),
+                        MoveTo(term_cols.saturating_sub(hint.len() as u16) / 2, end_row + 2),
+                        Print(theme.highlight().apply(hint.as_str()).bold()),
                     )
                     .ok();
                     Menu::draw_borders(start_row, end_row, start_col, end_col).ok();
-                    for (i, level) in repository.levels.iter().enumerate() {
-                        if let LevelSource::Url(url) = &level.source {
-                            let completed = completed_levels.iter().any(|l| {
-                                l.url == *url || (l.name == level.name && l.author == level.author)
-                            });
+                    if repository.levels.is_empty() {
+                        execute!(
+                            stdout(),
+                            MoveTo(start_col + 2, start_row + 1),
+                            Print("No levels found."),
+                        )
+                        .ok();
+                    }
+                    let ascii_only = Settings::active().ascii_only;
+                    for (i, level) in repository
+                        .levels
+                        .iter()
+                        .enumerate()
+                        .skip(start_index)
+                        .take(num_rows)
+                    {
+                        if let Some(url) = level.source.url() {
+                            let completed = completed_levels
+                                .iter()
+                                .any(|l| l.matches(&url, level.meta.hash.as_deref()));
                             execute!(
                                 stdout(),
-                                SetBackgroundColor(if i == current_selection {
-                                    Color::White
-                                } else {
-                                    Color::Reset
-                                }),
-                                SetForegroundColor(if i == current_selection {
-                                    Color::Black
-                                } else {
-                                    Color::White
-                                }),
-                                MoveTo(start_col + 1, start_row + i as u16 + 1),
+                                SetStyle(theme.item(i == current_selection)),
+                                MoveTo(start_col + 1, start_row + (i - start_index) as u16 + 1),
                                 Print(
                                     format!(
                                         " {} {: <level_name_len$} {: <level_author_len$} {: <level_desc_len$}",
-                                        if completed { "\u{2713}" } else { " " },
-                                        &level.name[0..level.name.len().min(level_name_len)],
-                                        &level.author[0..level.author.len().min(level_author_len)],
-                                        &level.description[0..level.description.len().min(level_desc_len)])
+                                        if !completed { " " } else if ascii_only { "*" } else { "\u{2713}" },
+                                        Menu::truncate(&level.name, level_name_len),
+                                        Menu::truncate(&level.author, level_author_len),
+                                        Menu::truncate(&level.description, level_desc_len))
                                     .bold()
                                 ),
                                 ResetColor
@@ -820,15 +1350,30 @@ impl Menu {
                             .ok();
                         }
                     }
-                    match Control::read_input() {
-                        Control::Up => {
+                    let control = Menu::select_clicked(
+                        Control::read_input(),
+                        &mut current_selection,
+                        |c, r| {
+                            (0..num_rows.min(repository.levels.len()))
+                                .find(|i| {
+                                    r == start_row + *i as u16 + 1 && c > start_col && c < end_col
+                                })
+                                .map(|i| start_index + i)
+                        },
+                    );
+                    match control {
+                        Control::Search => return Some(Selection::Search),
+                        Control::Quit => return Some(Selection::Quit),
+                        Control::None => continue,
+                        _ if repository.levels.is_empty() => continue,
+                        Control::Up | Control::ScrollUp => {
                             if current_selection == 0 {
                                 current_selection = repository.levels.len() - 1;
                             } else {
                                 current_selection -= 1;
                             }
                         }
-                        Control::Down => {
+                        Control::Down | Control::ScrollDown => {
                             if current_selection == repository.levels.len() - 1 {
                                 current_selection = 0;
                             } else {
@@ -836,13 +1381,10 @@ impl Menu {
                             }
                         }
                         Control::Select => return Some(Selection::Item(current_selection)),
-                        Control::Quit => return Some(Selection::Quit),
-                        Control::None => continue,
                         _ => break,
                     }
                 }
             }
-            _ => (),
         }
         None
     }
@@ -0,0 +1,63 @@
+use crate::direction::Direction;
+use serde::{Deserialize, Serialize};
+
+/// Something the player did while playing a level. Every move played is
+/// kept, so a win can be replayed to prove that a level can be won.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Move {
+    /// Moving the player one space.
+    Step(Direction),
+    /// The player action, which toggles every node next to the player.
+    Action,
+    /// Toggling just the node next to the player in a direction.
+    Toggle(Direction),
+    Pull,
+}
+
+impl Move {
+    /// Writes `moves` in the notation read by [`Move::parse`], where each
+    /// move is a single letter: `U`, `D`, `L` and `R` are steps, `u`, `d`,
+    /// `l` and `r` are toggles in a direction, `x` is the player action
+    /// and `p` is a pull.
+    pub fn notation(moves: &[Move]) -> String {
+        moves.iter().map(|m| m.letter()).collect()
+    }
+
+    /// Reads moves written by [`Move::notation`], ignoring whitespace.
+    pub fn parse(notation: &str) -> Result<Vec<Move>, String> {
+        notation
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .map(|c| match c {
+                'U' => Ok(Move::Step(Direction::UP)),
+                'D' => Ok(Move::Step(Direction::DOWN)),
+                'L' => Ok(Move::Step(Direction::LEFT)),
+                'R' => Ok(Move::Step(Direction::RIGHT)),
+                'u' => Ok(Move::Toggle(Direction::UP)),
+                'd' => Ok(Move::Toggle(Direction::DOWN)),
+                'l' => Ok(Move::Toggle(Direction::LEFT)),
+                'r' => Ok(Move::Toggle(Direction::RIGHT)),
+                'x' => Ok(Move::Action),
+                'p' => Ok(Move::Pull),
+                c => Err(format!("`{c}` isn't a move")),
+            })
+            .collect()
+    }
+
+    fn letter(&self) -> char {
+        match *self {
+            Move::Step(Direction::UP) => 'U',
+            Move::Step(Direction::DOWN) => 'D',
+            Move::Step(Direction::LEFT) => 'L',
+            Move::Step(Direction::RIGHT) => 'R',
+            Move::Toggle(Direction::UP) => 'u',
+            Move::Toggle(Direction::DOWN) => 'd',
+            Move::Toggle(Direction::LEFT) => 'l',
+            Move::Toggle(Direction::RIGHT) => 'r',
+            Move::Action => 'x',
+            Move::Pull => 'p',
+            // Only the four directions above can be played.
+            Move::Step(_) | Move::Toggle(_) => '?',
+        }
+    }
+}
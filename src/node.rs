@@ -1,52 +1,58 @@
-use crate::direction::Direction;
+use crate::{direction::Direction, theme::Theme};
 use crossterm::{
     cursor::MoveTo,
     execute,
-    style::{Color, Print, ResetColor, SetBackgroundColor, SetForegroundColor, Stylize},
+    style::{
+        Attribute, Color, Print, ResetColor, SetAttribute, SetBackgroundColor, SetForegroundColor,
+        Stylize,
+    },
 };
+use serde::{Deserialize, Serialize};
 use std::io::stdout;
 
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct Player {
     pub dead: bool,
+    pub facing: Direction,
 }
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct Block;
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct Wall;
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct Switch {
     pub on: bool,
 }
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct ToggleBlock {
     pub visible: bool,
 }
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct Button {
     pub pressed: bool,
 }
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct Mirror {
     pub dir: Direction,
 }
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct Laser {
     pub on: bool,
     pub dir: Direction,
+    #[serde(skip)]
     pub shooting_at: Vec<(u16, u16, char, char)>,
 }
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct Statue {
     pub lit: bool,
     pub reversed: bool,
 }
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct Zapper {
     pub lit: bool,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
 pub enum NodeType {
     Player(Player),
     Block(Block),
@@ -60,7 +66,7 @@ pub enum NodeType {
     Zapper(Zapper),
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct Node {
     pub node_type: NodeType,
     pub row: u16,
@@ -68,26 +74,16 @@ pub struct Node {
     moveable: bool,
 }
 
-const RED: Color = Color::Rgb { r: 255, g: 0, b: 0 };
-const DIM_RED: Color = Color::Rgb { r: 100, g: 0, b: 0 };
-const YELLOW: Color = Color::Rgb {
-    r: 255,
-    g: 255,
-    b: 0,
-};
-const DIM_YELLOW: Color = Color::Rgb {
-    r: 100,
-    g: 100,
-    b: 0,
-};
-
 impl Node {
     pub fn new(ch: char, row: u16, col: u16) -> Node {
         match ch {
             'X' => Node {
                 row,
                 col,
-                node_type: NodeType::Player(Player { dead: false }),
+                node_type: NodeType::Player(Player {
+                    dead: false,
+                    facing: Direction::DOWN,
+                }),
                 moveable: true,
             },
             'B' => Node {
@@ -259,7 +255,7 @@ impl Node {
         }
     }
 
-    pub fn draw_overlay(&self, offset: (u16, u16)) -> crossterm::Result<()> {
+    pub fn draw_overlay(&self, offset: (u16, u16), theme: &Theme) -> crossterm::Result<()> {
         let mut stdout = stdout();
         if let NodeType::Laser(l) = &self.node_type {
             if l.shooting_at.is_empty() {
@@ -269,7 +265,7 @@ impl Node {
                 let pos = l.shooting_at[i];
                 execute!(
                     stdout,
-                    SetForegroundColor(Color::Rgb { r: 255, g: 0, b: 0 }),
+                    SetForegroundColor(theme.beam),
                     MoveTo(pos.1 + offset.1, pos.0 + offset.0),
                 )?;
                 if i == l.shooting_at.len() - 2 {
@@ -282,111 +278,181 @@ impl Node {
         execute!(stdout, ResetColor)
     }
 
-    pub fn draw(&self, offset: (u16, u16)) -> crossterm::Result<()> {
+    /// The character a node is drawn as and the attribute it's drawn
+    /// with, or `None` for nodes that aren't drawn. Without colors these
+    /// are all that tells nodes and their states apart.
+    pub fn glyph(&self, theme: &Theme) -> Option<(char, Attribute)> {
+        let plain = Attribute::NoReverse;
+        Some(match &self.node_type {
+            NodeType::Player(p) => (
+                match p.facing {
+                    _ if p.dead && theme.monochrome => 'X',
+                    Direction::UP => '^',
+                    Direction::LEFT => '<',
+                    Direction::RIGHT => '>',
+                    _ => 'v',
+                },
+                plain,
+            ),
+            NodeType::Block(_) => ('B', plain),
+            NodeType::Wall(_) => ('I', plain),
+            NodeType::Switch(s) => ('s', reverse_if(s.on, theme)),
+            NodeType::ToggleBlock(t) if t.visible => ('T', plain),
+            NodeType::ToggleBlock(_) => return None,
+            NodeType::Button(b) => ('b', reverse_if(b.pressed, theme)),
+            NodeType::Mirror(m) => (
+                if matches!(m.dir, Direction::FORWARD) {
+                    '/'
+                } else {
+                    '\\'
+                },
+                reverse_if(self.moveable, theme),
+            ),
+            NodeType::Laser(l) => (state_letter('L', l.on, theme), plain),
+            // Unlit statues are underlined so that an unlit `s` can't be
+            // mistaken for a switch.
+            NodeType::Statue(s) => (
+                state_letter(if s.reversed { 'R' } else { 'S' }, s.lit, theme),
+                if theme.monochrome && !s.lit {
+                    Attribute::Underlined
+                } else {
+                    plain
+                },
+            ),
+            NodeType::Zapper(z) => (state_letter('Z', z.lit, theme), plain),
+        })
+    }
+
+    pub fn draw(&self, offset: (u16, u16), theme: &Theme) -> crossterm::Result<()> {
+        let Some((glyph, attribute)) = self.glyph(theme) else {
+            return Ok(());
+        };
         let mut stdout = stdout();
+        execute!(
+            stdout,
+            SetAttribute(Attribute::NoReverse),
+            SetAttribute(Attribute::NoUnderline),
+            SetAttribute(attribute)
+        )?;
         match &self.node_type {
             NodeType::Player(p) => execute!(
                 stdout,
-                SetForegroundColor(if p.dead { RED } else { Color::Green }),
-                SetBackgroundColor(Color::Green),
+                SetForegroundColor(if p.dead {
+                    theme.player_dead
+                } else {
+                    theme.contrast
+                }),
+                SetBackgroundColor(theme.player),
                 MoveTo(self.col + offset.1, self.row + offset.0),
-                Print("X".bold()),
+                Print(glyph.bold()),
             ),
             NodeType::Block(_) => execute!(
                 stdout,
-                SetForegroundColor(Color::Grey),
-                SetBackgroundColor(Color::Grey),
+                SetForegroundColor(theme.block),
+                SetBackgroundColor(theme.block),
                 MoveTo(self.col + offset.1, self.row + offset.0),
-                Print("B".bold()),
+                Print(glyph.bold()),
             ),
             NodeType::Wall(_) => execute!(
                 stdout,
-                SetForegroundColor(Color::White),
-                SetBackgroundColor(Color::White),
+                SetForegroundColor(theme.wall),
+                SetBackgroundColor(theme.wall),
                 MoveTo(self.col + offset.1, self.row + offset.0),
-                Print("I".bold()),
+                Print(glyph.bold()),
             ),
             NodeType::Switch(s) => execute!(
                 stdout,
-                SetForegroundColor(Color::Black),
-                SetBackgroundColor(if s.on { Color::Yellow } else { Color::Red }),
+                SetForegroundColor(theme.contrast),
+                SetBackgroundColor(if s.on { theme.on } else { theme.off }),
                 MoveTo(self.col + offset.1, self.row + offset.0),
-                Print("s".bold()),
+                Print(glyph.bold()),
+            ),
+            NodeType::ToggleBlock(_) => execute!(
+                stdout,
+                SetForegroundColor(theme.toggle_block),
+                SetBackgroundColor(theme.toggle_block),
+                MoveTo(self.col + offset.1, self.row + offset.0),
+                Print(glyph.bold())
             ),
-            NodeType::ToggleBlock(t) => {
-                if t.visible {
-                    execute!(
-                        stdout,
-                        SetForegroundColor(Color::Magenta),
-                        SetBackgroundColor(Color::Magenta),
-                        MoveTo(self.col + offset.1, self.row + offset.0),
-                        Print("T".bold())
-                    )
-                } else {
-                    Ok(())
-                }
-            }
             NodeType::Button(b) => execute!(
                 stdout,
-                SetForegroundColor(Color::Black),
-                SetBackgroundColor(if b.pressed { Color::Yellow } else { Color::Red }),
+                SetForegroundColor(theme.contrast),
+                SetBackgroundColor(if b.pressed { theme.on } else { theme.off }),
                 MoveTo(self.col + offset.1, self.row + offset.0),
-                Print("b".bold()),
+                Print(glyph.bold()),
             ),
-            NodeType::Mirror(m) => execute!(
+            NodeType::Mirror(_) => execute!(
                 stdout,
                 SetForegroundColor(if self.moveable {
-                    Color::Black
+                    theme.contrast
                 } else {
-                    Color::White
+                    theme.mirror
                 }),
                 SetBackgroundColor(if self.moveable {
-                    Color::White
+                    theme.moveable_mirror
                 } else {
                     Color::Reset
                 }),
                 MoveTo(self.col + offset.1, self.row + offset.0),
-                Print(if matches!(m.dir, Direction::FORWARD) {
-                    "/".bold()
-                } else {
-                    "\\".bold()
-                }),
+                Print(glyph.bold()),
             ),
             NodeType::Laser(l) => execute!(
                 stdout,
-                SetForegroundColor(if l.on { RED } else { DIM_RED }),
-                SetBackgroundColor(if l.on { RED } else { DIM_RED }),
+                SetForegroundColor(if l.on {
+                    theme.laser_on
+                } else {
+                    theme.laser_off
+                }),
+                SetBackgroundColor(if l.on {
+                    theme.laser_on
+                } else {
+                    theme.laser_off
+                }),
                 MoveTo(self.col + offset.1, self.row + offset.0),
-                Print("L".bold()),
+                Print(glyph.bold()),
             ),
             NodeType::Statue(s) => {
                 if s.reversed {
                     execute!(
                         stdout,
-                        SetForegroundColor(Color::Black),
-                        SetBackgroundColor(if s.lit { DIM_YELLOW } else { YELLOW }),
+                        SetForegroundColor(theme.contrast),
+                        SetBackgroundColor(if s.lit {
+                            theme.statue_unlit
+                        } else {
+                            theme.statue_lit
+                        }),
                         MoveTo(self.col + offset.1, self.row + offset.0),
-                        Print("R".bold()),
+                        Print(glyph.bold()),
                     )
                 } else {
                     execute!(
                         stdout,
-                        SetForegroundColor(if s.lit { YELLOW } else { DIM_YELLOW }),
-                        SetBackgroundColor(if s.lit { YELLOW } else { DIM_YELLOW }),
+                        // Lit statues show their letter so they can be told
+                        // apart by more than brightness.
+                        SetForegroundColor(if s.lit {
+                            theme.contrast
+                        } else {
+                            theme.statue_unlit
+                        }),
+                        SetBackgroundColor(if s.lit {
+                            theme.statue_lit
+                        } else {
+                            theme.statue_unlit
+                        }),
                         MoveTo(self.col + offset.1, self.row + offset.0),
-                        Print("S".bold()),
+                        Print(glyph.bold()),
                     )
                 }
             }
             NodeType::Zapper(z) => execute!(
                 stdout,
-                SetForegroundColor(if z.lit { Color::Black } else { Color::Yellow }),
-                SetBackgroundColor(if z.lit { Color::Yellow } else { Color::Black }),
+                SetForegroundColor(if z.lit { theme.contrast } else { theme.zapper }),
+                SetBackgroundColor(if z.lit { theme.zapper } else { Color::Reset }),
                 MoveTo(self.col + offset.1, self.row + offset.0),
-                Print("Z".bold()),
+                Print(glyph.bold()),
             ),
         }?;
-        execute!(stdout, ResetColor)
+        execute!(stdout, SetAttribute(Attribute::NoUnderline), ResetColor)
     }
 
     pub fn would_move_to(&mut self, dir: Direction) -> (u16, u16) {
@@ -486,3 +552,24 @@ impl Node {
         }
     }
 }
+
+/// The letter drawn for a node that can be on or off. Without colors
+/// there's nothing else to tell the two apart, so nodes that are off
+/// are drawn in lower case.
+fn state_letter(letter: char, on: bool, theme: &Theme) -> char {
+    if theme.monochrome && !on {
+        letter.to_ascii_lowercase()
+    } else {
+        letter
+    }
+}
+
+/// Without colors, switches and buttons that are on and moveable mirrors
+/// are drawn in reverse video instead.
+fn reverse_if(on: bool, theme: &Theme) -> Attribute {
+    if theme.monochrome && on {
+        Attribute::Reverse
+    } else {
+        Attribute::NoReverse
+    }
+}
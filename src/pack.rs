@@ -0,0 +1,134 @@
+use crate::level::{Level, LevelInfo, LevelSource};
+use serde::Deserialize;
+use std::{
+    fs,
+    io::Read,
+    path::{Component, Path, PathBuf},
+};
+
+/// The manifest at the root of a level pack, listing its levels in the
+/// order they're played.
+#[derive(Debug, Deserialize)]
+struct PackManifest {
+    name: String,
+    author: String,
+    #[serde(default)]
+    description: String,
+    /// The paths of the pack's level files, relative to the manifest.
+    levels: Vec<String>,
+}
+
+/// An ordered set of levels played one after another like the core
+/// levels, where each level is unlocked by completing the one before it.
+/// A pack is either a directory with a `pack.json` manifest and its
+/// level files, or a `.zip` archive of one.
+#[derive(Debug)]
+pub struct Pack {
+    /// Where the pack is, used to tell packs apart.
+    pub path: PathBuf,
+    pub name: String,
+    pub author: String,
+    pub description: String,
+    /// The pack's levels, in the order they're played.
+    pub levels: Vec<LevelInfo>,
+    contents: Vec<String>,
+}
+
+impl Pack {
+    pub const MANIFEST: &'static str = "pack.json";
+
+    /// Whether `path` looks like a pack, either a directory with a
+    /// manifest or a `.zip` archive.
+    pub fn is_pack(path: &Path) -> bool {
+        path.join(Pack::MANIFEST).is_file() || Pack::is_archive(path)
+    }
+
+    fn is_archive(path: &Path) -> bool {
+        path.is_file()
+            && path
+                .extension()
+                .is_some_and(|e| e.eq_ignore_ascii_case("zip"))
+    }
+
+    /// Reads the pack at `path` and every level in it, failing if any of
+    /// them can't be read or aren't valid levels.
+    pub fn open(path: &Path) -> Result<Pack, String> {
+        let path = path
+            .canonicalize()
+            .map_err(|e| format!("{}: {e}", path.display()))?;
+        if Pack::is_archive(&path) {
+            let file = fs::File::open(&path).map_err(|e| format!("{}: {e}", path.display()))?;
+            let mut archive =
+                zip::ZipArchive::new(file).map_err(|e| format!("{}: {e}", path.display()))?;
+            Pack::read(path, |name| {
+                let mut file = archive.by_name(name).map_err(|e| format!("{name}: {e}"))?;
+                let mut content = String::new();
+                file.read_to_string(&mut content)
+                    .map_err(|e| format!("{name}: {e}"))?;
+                Ok(content)
+            })
+        } else {
+            let dir = path.clone();
+            Pack::read(path, |name| {
+                fs::read_to_string(dir.join(name)).map_err(|e| format!("{name}: {e}"))
+            })
+        }
+    }
+
+    /// Reads the manifest and levels of the pack at `path` with
+    /// `read_file`, which reads a file in the pack by its relative path.
+    fn read(
+        path: PathBuf,
+        mut read_file: impl FnMut(&str) -> Result<String, String>,
+    ) -> Result<Pack, String> {
+        let manifest: PackManifest = serde_json::from_str(&read_file(Pack::MANIFEST)?)
+            .map_err(|e| format!("{}: {e}", Pack::MANIFEST))?;
+        if manifest.levels.is_empty() {
+            return Err(format!("{} doesn't list any levels", Pack::MANIFEST));
+        }
+        let mut levels = vec![];
+        let mut contents = vec![];
+        for (i, file) in manifest.levels.iter().enumerate() {
+            let inside = Path::new(file)
+                .components()
+                .all(|c| matches!(c, Component::Normal(_) | Component::CurDir));
+            if !inside {
+                return Err(format!("the level `{file}` is outside of the pack"));
+            }
+            let file = file.trim_start_matches("./");
+            let content = read_file(file)?;
+            let level = Level::parse(&content, Pack::source(&path, i))
+                .map_err(|e| format!("{file}: {e}"))?;
+            levels.push(level.info);
+            contents.push(content);
+        }
+        Ok(Pack {
+            path,
+            name: manifest.name,
+            author: manifest.author,
+            description: manifest.description,
+            levels,
+            contents,
+        })
+    }
+
+    fn source(path: &Path, level: usize) -> LevelSource {
+        LevelSource::Pack {
+            path: path.to_path_buf(),
+            level,
+        }
+    }
+
+    /// The key the player's progress in the pack is kept under.
+    pub fn key(&self) -> String {
+        self.path.to_string_lossy().to_string()
+    }
+
+    /// Loads the pack's level at `level`, counting from 0.
+    pub fn level(&self, level: usize) -> Result<Level, &'static str> {
+        match self.contents.get(level) {
+            Some(content) => Level::parse(content, Pack::source(&self.path, level)),
+            None => Err("The pack doesn't have that many levels."),
+        }
+    }
+}
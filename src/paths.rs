@@ -0,0 +1,106 @@
+use home::home_dir;
+use std::{env, fs, path};
+
+/// Where `l1t` keeps its files.
+///
+/// By default these follow the XDG base directory spec: config
+/// (repositories and key bindings) goes in `$XDG_CONFIG_HOME/l1t`,
+/// progress in `$XDG_DATA_HOME/l1t` and downloads in `$XDG_CACHE_HOME/l1t`.
+/// Setting `L1T_HOME` or passing `--data-dir` keeps everything in that
+/// one directory instead, which is useful for running a sandboxed copy.
+#[derive(Debug, Clone)]
+pub struct Paths {
+    pub config: String,
+    pub data: String,
+    pub cache: String,
+    /// No home directory could be found, so files are kept in a temporary
+    /// directory and will likely be lost.
+    pub temporary: bool,
+}
+
+/// The files that used to be kept in `$HOME/.l1t` and the directory each
+/// belongs in now.
+const LEGACY_CONFIG: [&str; 2] = ["keys.toml", "repositories.l1t_conf"];
+const LEGACY_DATA: [&str; 5] = ["profile", "profiles", "data.json", "data.json.bak", "saves"];
+
+impl Paths {
+    /// Finds the directories to use. `data_dir` comes from `--data-dir`
+    /// and takes priority over `L1T_HOME`.
+    pub fn new(data_dir: Option<String>) -> Paths {
+        let root = data_dir.or_else(|| env::var("L1T_HOME").ok().filter(|h| !h.is_empty()));
+        if let Some(root) = root {
+            return Paths::single(root);
+        }
+        let home = home_dir().map(|h| h.to_string_lossy().to_string());
+        let config = xdg_dir("XDG_CONFIG_HOME", &home, "/.config");
+        let data = xdg_dir("XDG_DATA_HOME", &home, "/.local/share");
+        let cache = xdg_dir("XDG_CACHE_HOME", &home, "/.cache");
+        match (config, data, cache) {
+            (Some(config), Some(data), Some(cache)) => {
+                let paths = Paths {
+                    config: config + "/l1t",
+                    data: data + "/l1t",
+                    cache: cache + "/l1t",
+                    temporary: false,
+                };
+                if let Some(home) = home {
+                    paths.migrate_legacy(&(home + "/.l1t"));
+                }
+                paths
+            }
+            _ => Paths {
+                temporary: true,
+                ..Paths::single(env::temp_dir().to_string_lossy().to_string() + "/l1t")
+            },
+        }
+    }
+
+    /// Keeps everything in `root`, laid out the same way as the old
+    /// `$HOME/.l1t` directory.
+    fn single(root: String) -> Paths {
+        Paths {
+            cache: root.to_string() + "/cache",
+            config: root.to_string(),
+            data: root,
+            temporary: false,
+        }
+    }
+
+    /// Moves the files from the old `$HOME/.l1t` directory into their
+    /// XDG directories, unless the XDG directories are already in use.
+    /// Anything that can't be moved is left where it is.
+    fn migrate_legacy(&self, old_dir: &str) {
+        if !path::Path::new(old_dir).is_dir()
+            || path::Path::new(&self.config).exists()
+            || path::Path::new(&self.data).exists()
+        {
+            return;
+        }
+        for (names, dir) in [
+            (&LEGACY_CONFIG[..], &self.config),
+            (&LEGACY_DATA[..], &self.data),
+        ] {
+            if fs::create_dir_all(dir).is_err() {
+                continue;
+            }
+            for name in names {
+                let from = format!("{old_dir}/{name}");
+                if path::Path::new(&from).exists() {
+                    fs::rename(&from, format!("{dir}/{name}")).ok();
+                }
+            }
+        }
+        // Only removed if everything was moved out of it.
+        fs::remove_dir(old_dir).ok();
+    }
+}
+
+/// The directory named by the environment variable `var`, or `fallback`
+/// inside the home directory. Relative paths are ignored, as the spec
+/// requires.
+fn xdg_dir(var: &str, home: &Option<String>, fallback: &str) -> Option<String> {
+    match env::var(var) {
+        Ok(dir) if path::Path::new(&dir).is_absolute() => Some(dir),
+        _ => home.as_ref().map(|h| h.to_string() + fallback),
+    }
+}
@@ -0,0 +1,126 @@
+use crate::{
+    level::{checksum, Level, LevelSource},
+    repository::{LevelMeta, LevelSize, RepositoryLevelInfo, RepositoryResponse, LISTING_VERSION},
+};
+use percent_encoding::{percent_decode_str, utf8_percent_encode, AsciiSet, CONTROLS};
+use std::{fs, path::Path, time::UNIX_EPOCH};
+
+/// The directory the levels of a repository are kept in, relative to its
+/// root.
+pub const LEVELS_DIR: &str = "levels";
+
+/// The characters escaped in the file names of listed levels, so that
+/// names like `#1?.l1t` aren't read as part of a URL's query or fragment.
+const FILE_NAME: &AsciiSet = &CONTROLS
+    .add(b' ')
+    .add(b'"')
+    .add(b'#')
+    .add(b'%')
+    .add(b'/')
+    .add(b'<')
+    .add(b'>')
+    .add(b'?')
+    .add(b'\\')
+    .add(b'`')
+    .add(b'{')
+    .add(b'}');
+
+/// A level's entry in a listing and the file served for it.
+pub type ListedLevel = (RepositoryLevelInfo, String);
+
+/// Builds a repository that any static web server can host from the
+/// `.l1t` files in `source`, writing each level and an `l1t` listing with
+/// their details and hashes to `out`. Every level is checked first, and
+/// nothing is written if any of them aren't valid. Returns the listed
+/// levels.
+pub fn build_repository(source: &Path, out: &Path) -> Result<Vec<RepositoryLevelInfo>, String> {
+    let same_dir = match (source.canonicalize(), out.canonicalize()) {
+        (Ok(source), Ok(out)) => source == out,
+        _ => false,
+    };
+    if same_dir {
+        return Err("the repository can't be built in the directory of its levels".to_string());
+    }
+    let (levels, errors) = listed_levels(source)?;
+    if levels.is_empty() && errors.is_empty() {
+        return Err(format!("{} doesn't have any .l1t files", source.display()));
+    }
+    if !errors.is_empty() {
+        return Err(errors.join("\n"));
+    }
+    let write = |path: &Path, content: &str| {
+        fs::write(path, content).map_err(|e| format!("{}: {e}", path.display()))
+    };
+    let levels_dir = out.join(LEVELS_DIR);
+    fs::create_dir_all(&levels_dir).map_err(|e| format!("{}: {e}", levels_dir.display()))?;
+    for (level, grid) in &levels {
+        let file = percent_decode_str(&level.source).decode_utf8_lossy();
+        write(&out.join(file.as_ref()), grid)?;
+    }
+    let listing = RepositoryResponse {
+        version: LISTING_VERSION,
+        levels: levels.into_iter().map(|(level, _)| level).collect(),
+        next: None,
+    };
+    let content = serde_json::to_string_pretty(&listing).map_err(|e| e.to_string())?;
+    write(&out.join("l1t"), &content)?;
+    Ok(listing.levels)
+}
+
+/// Checks every `.l1t` file in `source`, returning the entries in the
+/// listing and files to serve for the valid ones along with a
+/// `file: error` message for each one that isn't.
+pub fn listed_levels(source: &Path) -> Result<(Vec<ListedLevel>, Vec<String>), String> {
+    let files =
+        Level::available_levels(source).map_err(|e| format!("{}: {e}", source.display()))?;
+    let mut levels = vec![];
+    let mut errors = vec![];
+    for info in files {
+        let LevelSource::File(path) = &info.source else {
+            continue;
+        };
+        match listed_level(path) {
+            Ok(level) => levels.push(level),
+            Err(e) => errors.push(format!(
+                "{}: {e}",
+                path.file_name().unwrap_or_default().to_string_lossy()
+            )),
+        }
+    }
+    Ok((levels, errors))
+}
+
+/// Checks the level at `path` and returns its entry in the listing along
+/// with the file to serve for it. The listing has the level's name, author
+/// and description, so the served file only has its options and grid.
+pub fn listed_level(path: &Path) -> Result<ListedLevel, String> {
+    let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+    let content = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let level = Level::parse(&content, LevelSource::File(path.to_path_buf()))?;
+    let grid: Vec<&str> = content.trim().split('\n').skip(3).collect();
+    let grid = grid.join("\n") + "\n";
+    let last_modified = fs::metadata(path)
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_secs());
+    let info = RepositoryLevelInfo {
+        source: format!(
+            "{LEVELS_DIR}/{}",
+            utf8_percent_encode(&file_name, FILE_NAME)
+        ),
+        name: level.info.name,
+        author: level.info.author,
+        description: level.info.description,
+        meta: LevelMeta {
+            size: Some(LevelSize {
+                width: level.cols,
+                height: level.rows,
+            }),
+            hash: Some(checksum(&grid)),
+            last_modified,
+            ..LevelMeta::default()
+        },
+    };
+    Ok((info, grid))
+}
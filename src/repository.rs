@@ -1,25 +1,388 @@
-use crate::level::{LevelInfo, LevelSource};
-use serde::Deserialize;
-use std::error::Error;
+use crate::{
+    cache::{Cache, CacheEntry},
+    level::{Level, LevelInfo, LevelSource},
+    moves::Move,
+};
+use ed25519_dalek::{Signature, VerifyingKey};
+use reqwest::{
+    header::{
+        HeaderMap, HeaderName, HeaderValue, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED,
+    },
+    Certificate, Client, ClientBuilder, StatusCode, Url,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    error::Error,
+    fmt, fs,
+    path::{self, Path, PathBuf},
+    sync::OnceLock,
+    time::{Duration, Instant},
+};
 
-#[derive(Debug, Clone)]
+/// The header a repository can send a page of its listing's signature in.
+pub const SIGNATURE_HEADER: &str = "x-l1t-signature";
+
+/// The client used by repositories without any TLS or auth options.
+static CLIENT: OnceLock<Client> = OnceLock::new();
+
+/// How long to wait for a repository to accept a connection.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+/// How long to wait for a whole request to a repository.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(20);
+/// How many times a request is tried before giving up, when the
+/// repository can't be reached or has a server error.
+const MAX_ATTEMPTS: u32 = 3;
+/// How long to wait before the first retry, doubled for each one after.
+const RETRY_DELAY: Duration = Duration::from_millis(500);
+/// The newest listing format this version understands.
+pub const LISTING_VERSION: u32 = 2;
+/// The listing at the root of a git repository.
+const GIT_LISTING: &str = "l1t.json";
+/// Where levels are uploaded to, relative to a repository's root.
+pub const UPLOAD_PATH: &str = "levels";
+
+/// Where results are reported to, relative to a repository's root.
+pub const RESULTS_PATH: &str = "results";
+
+/// The most pages of a listing that are followed, in case a repository's
+/// pages lead back to each other.
+const MAX_LISTING_PAGES: usize = 100;
+
+/// A repository of levels, as configured in `repositories.toml`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Repository {
     pub name: String,
     pub url: String,
+    /// Disabled repositories are kept in the config but not listed.
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    /// The path of a PEM certificate to pin the repository to. Only a
+    /// server whose certificate is signed by it is accepted.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub certificate: Option<String>,
+    /// A header sent with every request to the repository, written as
+    /// `Name: value`, e.g. `Authorization: Bearer <token>`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auth_header: Option<String>,
+    /// The hex encoded ed25519 public key the repository's listing must
+    /// be signed with. Pinning a key also requires every level in the
+    /// listing to have a hash, so the levels are covered by the signature.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub public_key: Option<String>,
+    /// How many seconds a downloaded listing or level is used for before
+    /// it's checked for changes.
+    #[serde(default = "default_cache_ttl")]
+    pub cache_ttl: u64,
+    /// The order the repository's levels are listed in.
+    #[serde(default)]
+    pub sort: RepositorySort,
+    /// Where the repository's levels are listed from.
+    #[serde(default, skip_serializing_if = "RepositoryKind::is_listing")]
+    pub kind: RepositoryKind,
+    /// The branch, tag or commit the levels of a git repository are
+    /// listed from. Defaults to the checked out branch.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rev: Option<String>,
+    /// Report the moves and time of every level won from the repository
+    /// to it.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub report_results: bool,
+    #[serde(skip)]
     pub levels: Vec<LevelInfo>,
+    /// Whether `levels` is an old copy from the cache because the
+    /// repository couldn't be reached.
+    #[serde(skip)]
+    pub stale: bool,
+    #[serde(skip)]
+    fetched_at: Option<Instant>,
+    #[serde(skip)]
+    client: OnceLock<Client>,
+}
+
+/// Why something couldn't be downloaded from a repository.
+#[derive(Debug)]
+pub enum RepositoryError {
+    /// The repository's TLS or auth options are invalid.
+    Config(String),
+    /// The repository couldn't be reached.
+    Network(String),
+    /// The repository replied with an error status.
+    Status { url: String, status: StatusCode },
+    /// The listing isn't valid JSON in the expected format.
+    BadJson(String),
+    /// A level isn't a valid level.
+    BadLevel(String),
+    /// A level doesn't match its hash, the listing isn't signed with the
+    /// repository's pinned key, or it points outside of the repository.
+    Integrity(String),
+    /// A file in a local repository couldn't be read.
+    File { path: String, error: String },
+    /// A git repository couldn't be read.
+    Git(String),
+    /// The repository doesn't accept uploaded levels or results.
+    ReadOnly(String),
+    /// The repository turned down an uploaded level or result.
+    Rejected(String),
 }
 
-#[derive(Deserialize, Debug, Clone)]
+impl fmt::Display for RepositoryError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RepositoryError::Config(e) => write!(f, "the repository's options aren't valid, {e}"),
+            RepositoryError::Network(e) => write!(f, "couldn't reach the repository, {e}"),
+            RepositoryError::Status { url, status } => write!(f, "{url} returned {status}"),
+            RepositoryError::BadJson(e) => write!(f, "the repository's listing isn't valid, {e}"),
+            RepositoryError::BadLevel(e) => write!(f, "the level isn't valid, {e}"),
+            RepositoryError::Integrity(e) => write!(f, "the download can't be trusted, {e}"),
+            RepositoryError::File { path, error } => write!(f, "couldn't read {path}, {error}"),
+            RepositoryError::Git(e) => write!(f, "couldn't read the git repository, {e}"),
+            RepositoryError::ReadOnly(what) => write!(f, "the repository doesn't accept {what}"),
+            RepositoryError::Rejected(e) => write!(f, "the repository turned it down, {e}"),
+        }
+    }
+}
+
+impl RepositoryError {
+    /// Whether the repository couldn't be reached or failed on its side,
+    /// in which case an old copy of what was asked for can stand in.
+    fn is_unreachable(&self) -> bool {
+        match self {
+            RepositoryError::Network(_) => true,
+            RepositoryError::Status { status, .. } => status.is_server_error(),
+            _ => false,
+        }
+    }
+}
+
+impl Error for RepositoryError {}
+
+/// A file downloaded from a repository.
+pub struct Download {
+    pub content: String,
+    /// The signature the repository sent with the file, if any.
+    pub signature: Option<String>,
+    /// Whether this is an old copy from the cache because the repository
+    /// couldn't be reached.
+    pub stale: bool,
+}
+
+impl Download {
+    fn new(entry: CacheEntry, stale: bool) -> Download {
+        Download {
+            content: entry.content,
+            signature: entry.signature,
+            stale,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RepositorySort {
+    /// The order the repository lists them in.
+    #[default]
+    Listing,
+    Name,
+    /// By author, then by name.
+    Author,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RepositoryKind {
+    /// An `l1t` listing on a web server, or a directory of levels.
+    #[default]
+    Listing,
+    /// A git clone on this computer, with an `l1t.json` listing at its
+    /// root. Levels are read from the clone's history at `rev`.
+    Git,
+}
+
+impl RepositoryKind {
+    fn is_listing(&self) -> bool {
+        *self == RepositoryKind::Listing
+    }
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn is_false(b: &bool) -> bool {
+    !b
+}
+
+fn default_cache_ttl() -> u64 {
+    300
+}
+
+/// The layout of `repositories.toml`.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct RepositoriesFile {
+    #[serde(default)]
+    repository: Vec<Repository>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct RepositoryLevelInfo {
     pub source: String,
     pub name: String,
     pub author: String,
     pub description: String,
+    #[serde(flatten)]
+    pub meta: LevelMeta,
 }
 
-#[derive(Deserialize, Debug, Clone)]
+impl RepositoryLevelInfo {
+    /// The info of the level, loaded from `source`.
+    pub(crate) fn info(self, source: LevelSource) -> LevelInfo {
+        LevelInfo {
+            source,
+            name: self.name,
+            author: self.author,
+            description: self.description,
+            meta: self.meta,
+        }
+    }
+}
+
+/// A page of a repository's listing. Version 1 listings only have
+/// `levels`, with no details beyond their name, author and description.
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct RepositoryResponse {
+    #[serde(default = "default_version")]
+    pub version: u32,
     pub levels: Vec<RepositoryLevelInfo>,
+    /// Where the next page of the listing is, relative to the
+    /// repository's root, if there is one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next: Option<String>,
+}
+
+fn default_version() -> u32 {
+    1
+}
+
+/// Details about a level given by version 2 listings.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LevelMeta {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// How hard the level is, from 1 (easiest) to 5 (hardest).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub difficulty: Option<u8>,
+    /// The fewest moves the level can be won in.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub par: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub size: Option<LevelSize>,
+    /// The hex encoded SHA-256 hash of the level file.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hash: Option<String>,
+    /// When the level was last changed, in seconds since the Unix epoch.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_modified: Option<u64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LevelSize {
+    pub width: u16,
+    pub height: u16,
+}
+
+impl LevelMeta {
+    pub fn is_empty(&self) -> bool {
+        *self == LevelMeta::default()
+    }
+}
+
+/// A level uploaded to a repository, along with the moves that win it so
+/// the repository can check that it can be won.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct LevelUpload {
+    /// The whole `.l1t` file, including its name, author and description.
+    pub level: String,
+    /// The moves that win the level, written in [`Move::notation`].
+    pub solution: String,
+}
+
+/// A level won by a player, reported to the repository it's from.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct LevelReport {
+    /// The level's source in the listing.
+    pub level: String,
+    /// The hash of the level that was played, if it's known.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hash: Option<String>,
+    pub moves: usize,
+    /// How long the level took to win, in seconds.
+    pub time: u64,
+}
+
+/// Why a repository turned down an upload or report.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SubmissionError {
+    pub error: String,
+}
+
+/// A search of a repository's levels. It's sent to the repository as the
+/// `q` and `tag` query parameters of the listing, and also applied to the
+/// levels that come back, as version 1 listings ignore them.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ListingQuery {
+    pub text: String,
+    pub tags: Vec<String>,
+}
+
+impl ListingQuery {
+    /// Parses a search typed by the player, where each word written as
+    /// `tag:name` is a tag the levels must have and the rest of the words
+    /// are text to search for.
+    pub fn parse(input: &str) -> ListingQuery {
+        let mut words = vec![];
+        let mut tags = vec![];
+        for word in input.split_whitespace() {
+            match word.strip_prefix("tag:") {
+                Some(tag) if !tag.is_empty() => tags.push(tag.to_string()),
+                _ => words.push(word),
+            }
+        }
+        ListingQuery {
+            text: words.join(" "),
+            tags,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.text.is_empty() && self.tags.is_empty()
+    }
+
+    fn apply(&self, url: &mut Url) {
+        if !self.text.is_empty() {
+            url.query_pairs_mut().append_pair("q", &self.text);
+        }
+        for tag in &self.tags {
+            url.query_pairs_mut().append_pair("tag", tag);
+        }
+    }
+
+    /// Whether `level` has every tag in the search and has the text in its
+    /// name, author, description or tags.
+    pub fn matches(&self, level: &LevelInfo) -> bool {
+        let has_tags = self.tags.iter().all(|tag| {
+            level
+                .meta
+                .tags
+                .iter()
+                .any(|t| t.to_lowercase() == tag.to_lowercase())
+        });
+        let text = self.text.to_lowercase();
+        let has_text = [&level.name, &level.author, &level.description]
+            .into_iter()
+            .chain(level.meta.tags.iter())
+            .any(|field| field.to_lowercase().contains(&text));
+        has_tags && has_text
+    }
 }
 
 impl Repository {
@@ -27,37 +390,681 @@ impl Repository {
         Repository {
             name,
             url,
+            enabled: true,
+            certificate: None,
+            auth_header: None,
+            public_key: None,
+            cache_ttl: default_cache_ttl(),
+            sort: RepositorySort::default(),
+            kind: RepositoryKind::default(),
+            rev: None,
+            report_results: false,
             levels: vec![],
+            stale: false,
+            fetched_at: None,
+            client: OnceLock::new(),
         }
     }
 
-    pub async fn download_listing(&mut self) -> Result<(), Box<dyn Error>> {
-        let response = reqwest::get(self.url.to_string() + "/l1t")
-            .await?
-            .text()
+    /// Reads the repositories in `repositories.toml` in `config_dir`. An
+    /// old `repositories.l1t_conf` file is converted to it first and kept
+    /// as `repositories.l1t_conf.bak`.
+    pub fn read_all(config_dir: &str) -> Result<Vec<Repository>, String> {
+        let file = config_dir.to_string() + "/repositories.toml";
+        let old_file = config_dir.to_string() + "/repositories.l1t_conf";
+        if !path::Path::new(&file).exists() {
+            let repositories = match fs::read_to_string(&old_file) {
+                Ok(content) => Repository::parse_old_config(&content),
+                Err(_) => vec![],
+            };
+            Repository::write_all(config_dir, &repositories)?;
+            if path::Path::new(&old_file).exists() {
+                fs::rename(&old_file, old_file.to_string() + ".bak").map_err(|e| e.to_string())?;
+            }
+        }
+        let content = fs::read_to_string(&file).map_err(|e| e.to_string())?;
+        let repositories: RepositoriesFile =
+            toml::from_str(&content).map_err(|e| format!("{file}: {}", e.message()))?;
+        Ok(repositories.repository)
+    }
+
+    /// Parses the old config format, where each line is `name = url`.
+    /// Only the first `=` separates the two, as URLs can contain them.
+    fn parse_old_config(content: &str) -> Vec<Repository> {
+        content
+            .lines()
+            .filter_map(|line| line.split_once('='))
+            .map(|(name, url)| Repository::new(name.trim().to_string(), url.trim().to_string()))
+            .filter(|r| !r.name.is_empty() && !r.url.is_empty())
+            .collect()
+    }
+
+    /// Writes `repositories` to `repositories.toml` in `config_dir`.
+    pub fn write_all(config_dir: &str, repositories: &[Repository]) -> Result<(), String> {
+        fs::create_dir_all(config_dir).map_err(|e| e.to_string())?;
+        let content = toml::to_string(&RepositoriesFile {
+            repository: repositories.to_vec(),
+        })
+        .map_err(|e| e.to_string())?;
+        let content = "# l1t repositories, see REPO.md for the options each one can have.\n\n"
+            .to_string()
+            + &content;
+        fs::write(config_dir.to_string() + "/repositories.toml", content).map_err(|e| e.to_string())
+    }
+
+    /// The HTTP client for the repository, set up with its TLS and auth
+    /// options. It's only built once and shared by every request.
+    fn client(&self) -> Result<Client, RepositoryError> {
+        if self.certificate.is_none() && self.auth_header.is_none() {
+            return Ok(CLIENT
+                .get_or_init(|| {
+                    Repository::client_builder()
+                        .build()
+                        .expect("default HTTP client can be built")
+                })
+                .clone());
+        }
+        if let Some(client) = self.client.get() {
+            return Ok(client.clone());
+        }
+        let config = |e: &dyn fmt::Display| RepositoryError::Config(e.to_string());
+        let mut builder = Repository::client_builder();
+        if let Some(certificate) = &self.certificate {
+            let pem = fs::read(certificate).map_err(|e| config(&format!("{certificate}: {e}")))?;
+            let certificate = Certificate::from_pem(&pem).map_err(|e| config(&e))?;
+            builder = builder
+                .tls_built_in_root_certs(false)
+                .add_root_certificate(certificate);
+        }
+        if let Some(header) = &self.auth_header {
+            let (name, value) = header
+                .split_once(':')
+                .ok_or_else(|| config(&"auth_header must be written as `Name: value`"))?;
+            let mut value = HeaderValue::from_str(value.trim()).map_err(|e| config(&e))?;
+            value.set_sensitive(true);
+            let name = HeaderName::from_bytes(name.trim().as_bytes()).map_err(|e| config(&e))?;
+            let mut headers = HeaderMap::new();
+            headers.insert(name, value);
+            builder = builder.default_headers(headers);
+        }
+        let client = builder.build().map_err(|e| config(&e))?;
+        self.client.set(client.clone()).ok();
+        Ok(client)
+    }
+
+    fn client_builder() -> ClientBuilder {
+        Client::builder()
+            .connect_timeout(CONNECT_TIMEOUT)
+            .timeout(REQUEST_TIMEOUT)
+    }
+
+    /// Downloads the list of levels in the repository, unless it was
+    /// downloaded less than `cache_ttl` seconds ago. If the repository
+    /// can't be reached, the copy in `cache` is used and marked as stale.
+    /// Local repositories are read again every time.
+    pub async fn download_listing(&mut self, cache: &Cache) -> Result<(), RepositoryError> {
+        if let (Some(fetched_at), None) = (self.fetched_at, self.local_dir()) {
+            if !self.stale && fetched_at.elapsed() < Duration::from_secs(self.cache_ttl) {
+                return Ok(());
+            }
+        }
+        let (levels, stale) = self
+            .read_listing(&ListingQuery::default(), cache, false)
+            .await?;
+        self.set_levels(levels);
+        self.stale = stale;
+        Ok(())
+    }
+
+    /// Downloads the listing and every level in the repository to `cache`
+    /// so they can be played offline. Returns how many levels were
+    /// downloaded, which is less than the number of levels if some
+    /// couldn't be.
+    pub async fn download_all(&mut self, cache: &Cache) -> Result<usize, RepositoryError> {
+        let (levels, _) = self
+            .read_listing(&ListingQuery::default(), cache, true)
             .await?;
-        let response: RepositoryResponse = match serde_json::from_str(&response) {
-            Ok(d) => d,
-            Err(e) => {
-                eprintln!("Error: {e}");
-                RepositoryResponse { levels: vec![] }
+        self.set_levels(levels);
+        self.stale = false;
+        if self.local_dir().is_some() {
+            return Ok(self.levels.len());
+        }
+        let mut downloaded = 0;
+        for level in &self.levels {
+            if let LevelSource::Url(url) = &level.source {
+                if let Ok(entry) = self.fetch(url, cache.get(url).as_ref()).await {
+                    if cache.put(&entry).is_ok() {
+                        downloaded += 1;
+                    }
+                }
             }
-        };
-        self.levels = response
-            .levels
-            .iter()
-            .map(|i| LevelInfo {
-                source: LevelSource::Url(self.url.to_string() + "/" + &i.source),
-                name: i.name.to_string(),
-                author: i.author.to_string(),
-                description: i.description.to_string(),
-            })
+        }
+        Ok(downloaded)
+    }
+
+    /// Downloads the list of levels in the repository, even if it was
+    /// downloaded recently, failing if the listing isn't valid. Used to
+    /// check a repository before it's saved.
+    pub async fn validate(&mut self, cache: &Cache) -> Result<(), RepositoryError> {
+        let (levels, _) = self
+            .read_listing(&ListingQuery::default(), cache, true)
+            .await?;
+        self.set_levels(levels);
+        self.stale = false;
+        Ok(())
+    }
+
+    /// Searches the repository's levels, returning a copy of the
+    /// repository with only the levels that match `query`.
+    pub async fn search(
+        &self,
+        query: &ListingQuery,
+        cache: &Cache,
+    ) -> Result<Repository, RepositoryError> {
+        let (levels, stale) = self.read_listing(query, cache, false).await?;
+        let mut results = self.clone();
+        results.set_levels(levels.into_iter().filter(|l| query.matches(l)).collect());
+        results.stale = stale;
+        Ok(results)
+    }
+
+    /// Downloads every page of the listing for `query`. Pages are taken
+    /// from `cache` like any other download unless `fresh` is set, in
+    /// which case they're always revalidated with the repository. Returns
+    /// the levels and whether any page was stale.
+    async fn read_listing(
+        &self,
+        query: &ListingQuery,
+        cache: &Cache,
+        fresh: bool,
+    ) -> Result<(Vec<LevelInfo>, bool), RepositoryError> {
+        if self.kind == RepositoryKind::Git {
+            return Ok((self.git_listing().await?, false));
+        }
+        if let Some(dir) = self.local_dir() {
+            if !dir.join("l1t").exists() {
+                return Ok((self.directory_listing(&dir)?, false));
+            }
+        }
+        let mut url = self.listing_url()?;
+        query.apply(&mut url);
+        let mut levels = vec![];
+        let mut stale = false;
+        for _ in 0..MAX_LISTING_PAGES {
+            let download = if fresh && !is_local(url.as_str()) {
+                let entry = self
+                    .fetch(url.as_str(), cache.get(url.as_str()).as_ref())
+                    .await?;
+                cache.put(&entry).ok();
+                Download::new(entry, false)
+            } else {
+                self.download(url.as_str(), cache).await?
+            };
+            stale |= download.stale;
+            if let Some(key) = &self.public_key {
+                let signature = match download.signature {
+                    Some(signature) => signature,
+                    None if levels.is_empty() => self.listing_signature(cache).await?,
+                    None => return Err(RepositoryError::Integrity(format!("{url} isn't signed"))),
+                };
+                verify_signature(key, &download.content, &signature)?;
+            }
+            let response = parse_listing(&download.content)?;
+            for level in response.levels {
+                let url = self.listed_level_url(&level)?;
+                levels.push(level.info(LevelSource::Url(url)));
+            }
+            match response.next {
+                Some(next) => url = self.resolve(&next, "next page")?,
+                None => return Ok((levels, stale)),
+            }
+        }
+        Err(RepositoryError::BadJson(format!(
+            "the listing has more than {MAX_LISTING_PAGES} pages"
+        )))
+    }
+
+    /// The URL of a level in the listing, checking that it has a hash if
+    /// the repository has a pinned key.
+    fn listed_level_url(&self, level: &RepositoryLevelInfo) -> Result<String, RepositoryError> {
+        if self.public_key.is_some() && level.meta.hash.is_none() {
+            return Err(RepositoryError::Integrity(format!(
+                "the level `{}` has no hash to check it with",
+                level.name
+            )));
+        }
+        Ok(self.resolve(&level.source, "level source")?.to_string())
+    }
+
+    /// The levels in the `l1t.json` listing of a git repository at `rev`.
+    /// If the repository has a pinned key, the listing must be signed in
+    /// an `l1t.json.sig` file next to it.
+    async fn git_listing(&self) -> Result<Vec<LevelInfo>, RepositoryError> {
+        let dir = self.git_dir()?;
+        let commit = git(
+            &dir,
+            &["rev-parse", "--verify", "--end-of-options", &self.git_rev()],
+        )
+        .await?;
+        let commit = commit.trim();
+        let content = git_file(&dir, commit, GIT_LISTING).await?;
+        if let Some(key) = &self.public_key {
+            let signature = git_file(&dir, commit, &(GIT_LISTING.to_string() + ".sig"))
+                .await
+                .map_err(|_| RepositoryError::Integrity("the listing isn't signed".to_string()))?;
+            verify_signature(key, &content, &signature)?;
+        }
+        let response = parse_listing(&content)?;
+        let mut levels = vec![];
+        for level in response.levels {
+            let url = self.listed_level_url(&level)?;
+            let source = LevelSource::Git {
+                url,
+                commit: commit.to_string(),
+            };
+            levels.push(level.info(source));
+        }
+        Ok(levels)
+    }
+
+    /// The `rev` to list levels from, with `^{commit}` added so tags are
+    /// followed to the commit they point to.
+    fn git_rev(&self) -> String {
+        format!("{}^{{commit}}", self.rev.as_deref().unwrap_or("HEAD"))
+    }
+
+    /// The directory of the git clone, which must be on this computer.
+    fn git_dir(&self) -> Result<PathBuf, RepositoryError> {
+        self.local_dir().ok_or_else(|| {
+            RepositoryError::Config(
+                "git repositories must be a clone on this computer, set up with a path or file:// URL"
+                    .to_string(),
+            )
+        })
+    }
+
+    /// Reads the level at `url` from the git repository as it was at
+    /// `commit`.
+    pub async fn git_level(&self, url: &str, commit: &str) -> Result<String, RepositoryError> {
+        let dir = self.git_dir()?;
+        let path = Url::parse(url)
+            .ok()
+            .and_then(|url| url.to_file_path().ok())
+            .and_then(|path| path.strip_prefix(&dir).ok().map(|p| p.to_path_buf()))
+            .ok_or_else(|| RepositoryError::Git(format!("{url} isn't in {}", dir.display())))?;
+        let path: Vec<String> = path
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy().to_string())
             .collect();
+        git_file(&dir, commit, &path.join("/")).await
+    }
+
+    /// The signature of the first page of the listing from the `l1t.sig`
+    /// file, for repositories that can't send it in a header.
+    async fn listing_signature(&self, cache: &Cache) -> Result<String, RepositoryError> {
+        let url = self.resolve("l1t.sig", "signature")?;
+        match self.download(url.as_str(), cache).await {
+            Ok(download) => Ok(download.content.trim().to_string()),
+            Err(RepositoryError::Status { .. } | RepositoryError::File { .. }) => Err(
+                RepositoryError::Integrity("the listing isn't signed".to_string()),
+            ),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// The levels of a local repository without a listing, made from the
+    /// `.l1t` files in its directory. Unlike the levels in a listing, these
+    /// are whole level files with a `name`, `author` and `description`.
+    fn directory_listing(&self, dir: &Path) -> Result<Vec<LevelInfo>, RepositoryError> {
+        if self.public_key.is_some() {
+            return Err(RepositoryError::Integrity(
+                "the listing isn't signed".to_string(),
+            ));
+        }
+        Level::available_levels(dir).map_err(|error| RepositoryError::File {
+            path: dir.display().to_string(),
+            error,
+        })
+    }
+
+    /// The directory of a repository on this computer, set up with a
+    /// `file://` URL or a path.
+    pub fn local_dir(&self) -> Option<PathBuf> {
+        self.root_url()
+            .ok()
+            .filter(|url| url.scheme() == "file")
+            .and_then(|url| url.to_file_path().ok())
+            .map(|path| path.components().collect())
+    }
+
+    /// The repository's URL with a trailing `/`, so paths are joined onto
+    /// it rather than replacing its last segment. Repositories set up with
+    /// a path rather than a URL are turned into a `file://` URL.
+    pub fn root_url(&self) -> Result<Url, RepositoryError> {
+        let mut url = match local_path(&self.url) {
+            Some(path) => Url::from_directory_path(&path).map_err(|_| {
+                RepositoryError::Config(format!("`{}` isn't a valid path", self.url))
+            })?,
+            None => Url::parse(&self.url).map_err(|e| {
+                RepositoryError::Config(format!("`{}` isn't a valid URL, {e}", self.url))
+            })?,
+        };
+        if !url.path().ends_with('/') {
+            url.set_path(&(url.path().to_string() + "/"));
+        }
+        Ok(url)
+    }
+
+    /// The URL of the repository's listing, `l1t` under its root.
+    pub fn listing_url(&self) -> Result<Url, RepositoryError> {
+        self.root_url()?
+            .join("l1t")
+            .map_err(|e| RepositoryError::Config(e.to_string()))
+    }
+
+    /// The URL of a level from its `source` in the listing, which is
+    /// relative to the repository's root and can't be outside of it.
+    pub fn level_url(&self, source: &str) -> Result<Url, RepositoryError> {
+        self.resolve(source, "level source")
+    }
+
+    /// Joins `path`, the listing's `what`, onto the repository's root,
+    /// failing if the result is outside of the repository.
+    fn resolve(&self, path: &str, what: &str) -> Result<Url, RepositoryError> {
+        let root = self.root_url()?;
+        let outside = || {
+            RepositoryError::Integrity(format!("the {what} `{path}` is outside of the repository"))
+        };
+        let url = root.join(path).map_err(|_| outside())?;
+        // `file:` URLs have opaque origins that never equal each other, so
+        // the parts of the origin are compared instead.
+        let origin = |url: &Url| {
+            (
+                url.scheme().to_string(),
+                url.host_str().map(|h| h.to_string()),
+                url.port_or_known_default(),
+            )
+        };
+        if origin(&url) != origin(&root) || !url.path().starts_with(root.path()) {
+            return Err(outside());
+        }
+        Ok(url)
+    }
+
+    /// Replaces `levels` with `levels`, sorted the way the repository is
+    /// set up to sort them.
+    fn set_levels(&mut self, mut levels: Vec<LevelInfo>) {
+        match self.sort {
+            RepositorySort::Listing => (),
+            RepositorySort::Name => levels.sort_by(|a, b| human_sort::compare(&a.name, &b.name)),
+            RepositorySort::Author => levels.sort_by(|a, b| {
+                human_sort::compare(&a.author, &b.author)
+                    .then_with(|| human_sort::compare(&a.name, &b.name))
+            }),
+        }
+        self.levels = levels;
+        self.fetched_at = Some(Instant::now());
+    }
+
+    /// Drops the levels listed so far, so the next listing is downloaded
+    /// again instead of being taken from where the repository used to be.
+    pub fn forget_levels(&mut self) {
+        self.levels = vec![];
+        self.stale = false;
+        self.fetched_at = None;
+    }
+
+    /// Uploads the `.l1t` file `level` to the repository with the moves
+    /// that win it, returning its entry in the listing.
+    pub async fn upload_level(
+        &self,
+        level: &str,
+        solution: &[Move],
+    ) -> Result<RepositoryLevelInfo, RepositoryError> {
+        let upload = LevelUpload {
+            level: level.to_string(),
+            solution: Move::notation(solution),
+        };
+        let content = self.submit(UPLOAD_PATH, "levels", &upload).await?;
+        serde_json::from_str(&content).map_err(|e| RepositoryError::BadJson(e.to_string()))
+    }
+
+    /// Reports that the repository's level `info` was won in `moves` moves
+    /// taking `time`.
+    pub async fn report_result(
+        &self,
+        info: &LevelInfo,
+        moves: usize,
+        time: Duration,
+    ) -> Result<(), RepositoryError> {
+        let root = self.root_url()?;
+        let level = info
+            .source
+            .url()
+            .and_then(|url| url.strip_prefix(root.as_str()).map(|s| s.to_string()))
+            .ok_or_else(|| RepositoryError::Config(format!("{} isn't from it", info.name)))?;
+        let report = LevelReport {
+            level,
+            hash: info.meta.hash.clone(),
+            moves,
+            time: time.as_secs(),
+        };
+        self.submit(RESULTS_PATH, "results", &report).await?;
         Ok(())
     }
 
-    pub async fn download_from_url(url: String) -> Result<String, Box<dyn Error>> {
-        let response = reqwest::get(url).await?.text().await?;
-        Ok(response)
+    /// Posts `body` as JSON to `path` under the repository's root,
+    /// returning the reply. Repositories that aren't served over HTTP, or
+    /// don't have `path`, don't accept `what`. Submissions aren't retried
+    /// as the repository may have already accepted them.
+    async fn submit(
+        &self,
+        path: &str,
+        what: &str,
+        body: &impl Serialize,
+    ) -> Result<String, RepositoryError> {
+        let url = self.resolve(path, what)?;
+        if self.kind == RepositoryKind::Git || !url.scheme().starts_with("http") {
+            return Err(RepositoryError::ReadOnly(what.to_string()));
+        }
+        let response = self
+            .client()?
+            .post(url.clone())
+            .json(body)
+            .send()
+            .await
+            .map_err(|e| RepositoryError::Network(e.to_string()))?;
+        let status = response.status();
+        let content = response
+            .text()
+            .await
+            .map_err(|e| RepositoryError::Network(e.to_string()))?;
+        match status {
+            s if s.is_success() => Ok(content),
+            StatusCode::NOT_FOUND
+            | StatusCode::METHOD_NOT_ALLOWED
+            | StatusCode::NOT_IMPLEMENTED => Err(RepositoryError::ReadOnly(what.to_string())),
+            s if s.is_client_error() => match serde_json::from_str::<SubmissionError>(&content) {
+                Ok(e) => Err(RepositoryError::Rejected(e.error)),
+                Err(_) => Err(RepositoryError::Status {
+                    url: url.to_string(),
+                    status,
+                }),
+            },
+            _ => Err(RepositoryError::Status {
+                url: url.to_string(),
+                status,
+            }),
+        }
+    }
+
+    /// Downloads `url`, which should be in the repository, with the
+    /// repository's options. The copy in `cache` is used if it was checked
+    /// less than `cache_ttl` seconds ago, and is revalidated with the server
+    /// otherwise. If the server can't be reached or fails with a server
+    /// error, the cached copy is returned as stale. Files in local repositories are always read from
+    /// the disk and never cached.
+    pub async fn download(&self, url: &str, cache: &Cache) -> Result<Download, RepositoryError> {
+        if is_local(url) {
+            return Ok(Download::new(read_file(url)?, false));
+        }
+        let cached = cache.get(url);
+        if let Some(entry) = &cached {
+            if entry.age() < self.cache_ttl {
+                return Ok(Download::new(entry.clone(), false));
+            }
+        }
+        match (self.fetch(url, cached.as_ref()).await, cached) {
+            (Ok(entry), _) => {
+                cache.put(&entry).ok();
+                Ok(Download::new(entry, false))
+            }
+            (Err(e), Some(entry)) if e.is_unreachable() => Ok(Download::new(entry, true)),
+            (Err(e), _) => Err(e),
+        }
+    }
+
+    /// Downloads `url`, sending the validators of `cached` so the server
+    /// can reply that it hasn't changed. Requests that can't connect, time
+    /// out or get a server error are retried a few times.
+    async fn fetch(
+        &self,
+        url: &str,
+        cached: Option<&CacheEntry>,
+    ) -> Result<CacheEntry, RepositoryError> {
+        if is_local(url) {
+            return read_file(url);
+        }
+        let client = self.client()?;
+        let mut attempt = 1;
+        let response = loop {
+            let mut request = client.get(url);
+            if let Some(entry) = cached {
+                if let Some(etag) = &entry.etag {
+                    request = request.header(IF_NONE_MATCH, etag);
+                }
+                if let Some(last_modified) = &entry.last_modified {
+                    request = request.header(IF_MODIFIED_SINCE, last_modified);
+                }
+            }
+            match request.send().await {
+                Ok(r) if r.status().is_server_error() && attempt < MAX_ATTEMPTS => (),
+                Err(e) if (e.is_connect() || e.is_timeout()) && attempt < MAX_ATTEMPTS => (),
+                Ok(r) => break r,
+                Err(e) => return Err(RepositoryError::Network(e.to_string())),
+            }
+            tokio::time::sleep(RETRY_DELAY * 2u32.pow(attempt - 1)).await;
+            attempt += 1;
+        };
+        if let (StatusCode::NOT_MODIFIED, Some(entry)) = (response.status(), cached) {
+            return Ok(entry.clone().refreshed());
+        }
+        if !response.status().is_success() {
+            return Err(RepositoryError::Status {
+                url: url.to_string(),
+                status: response.status(),
+            });
+        }
+        let header = |name| {
+            response
+                .headers()
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .map(|v| v.to_string())
+        };
+        let etag = header(ETAG);
+        let last_modified = header(LAST_MODIFIED);
+        let signature = header(HeaderName::from_static(SIGNATURE_HEADER));
+        let content = response
+            .text()
+            .await
+            .map_err(|e| RepositoryError::Network(e.to_string()))?;
+        Ok(CacheEntry {
+            signature,
+            ..CacheEntry::new(url, etag, last_modified, content)
+        })
+    }
+}
+
+/// Parses a page of a listing, failing if it's a newer version than this
+/// version of `l1t` understands.
+fn parse_listing(content: &str) -> Result<RepositoryResponse, RepositoryError> {
+    let response: RepositoryResponse =
+        serde_json::from_str(content).map_err(|e| RepositoryError::BadJson(e.to_string()))?;
+    if response.version > LISTING_VERSION {
+        return Err(RepositoryError::BadJson(format!(
+            "version {} listings aren't supported by this version of l1t",
+            response.version
+        )));
+    }
+    Ok(response)
+}
+
+/// Runs `git` in `dir` with `args`, returning what it printed.
+async fn git(dir: &Path, args: &[&str]) -> Result<String, RepositoryError> {
+    let output = tokio::process::Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        .output()
+        .await
+        .map_err(|e| RepositoryError::Git(format!("couldn't run git, {e}")))?;
+    if !output.status.success() {
+        let error = String::from_utf8_lossy(&output.stderr);
+        return Err(RepositoryError::Git(error.trim().to_string()));
+    }
+    String::from_utf8(output.stdout).map_err(|e| RepositoryError::Git(e.to_string()))
+}
+
+/// The content of the file at `path` in `commit` of the git repository.
+async fn git_file(dir: &Path, commit: &str, path: &str) -> Result<String, RepositoryError> {
+    git(dir, &["cat-file", "blob", &format!("{commit}:{path}")]).await
+}
+
+/// Whether `url` is a file on this computer rather than on a server.
+fn is_local(url: &str) -> bool {
+    url.starts_with("file:")
+}
+
+/// Reads `url`, a `file:` URL, from the disk.
+fn read_file(url: &str) -> Result<CacheEntry, RepositoryError> {
+    let path = Url::parse(url)
+        .ok()
+        .and_then(|url| url.to_file_path().ok())
+        .ok_or_else(|| RepositoryError::Config(format!("`{url}` isn't a valid file URL")))?;
+    let content = fs::read_to_string(&path).map_err(|e| RepositoryError::File {
+        path: path.display().to_string(),
+        error: e.to_string(),
+    })?;
+    Ok(CacheEntry::new(url, None, None, content))
+}
+
+/// The path a repository's URL refers to when it's written as a path,
+/// like `~/levels` or `/srv/levels`, rather than a URL.
+pub fn local_path(url: &str) -> Option<PathBuf> {
+    match url.strip_prefix("~/") {
+        Some(rest) => Some(home::home_dir()?.join(rest)),
+        None if url.starts_with('/') => Some(PathBuf::from(url)),
+        None => None,
     }
 }
+
+/// Checks that `signature`, a hex encoded ed25519 signature, is a
+/// signature of `content` by the hex encoded public `key`.
+pub fn verify_signature(key: &str, content: &str, signature: &str) -> Result<(), RepositoryError> {
+    let key: [u8; 32] = hex::decode(key.trim())
+        .ok()
+        .and_then(|k| k.try_into().ok())
+        .ok_or_else(|| {
+            RepositoryError::Config("public_key must be 64 hex characters".to_string())
+        })?;
+    let key = VerifyingKey::from_bytes(&key)
+        .map_err(|_| RepositoryError::Config("public_key isn't a valid ed25519 key".to_string()))?;
+    let invalid = || RepositoryError::Integrity("the listing's signature isn't valid".to_string());
+    let signature: [u8; 64] = hex::decode(signature.trim())
+        .ok()
+        .and_then(|s| s.try_into().ok())
+        .ok_or_else(invalid)?;
+    key.verify_strict(content.as_bytes(), &Signature::from_bytes(&signature))
+        .map_err(|_| invalid())
+}
@@ -0,0 +1,86 @@
+use crate::{
+    level::{Level, LevelInfo, LevelSource},
+    moves::Move,
+    node::{Node, NodeType},
+};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// The state of a level the player quit part way through, so it
+/// can be resumed later.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SavedLevel {
+    pub info: LevelInfo,
+    /// The checksum of the level when it was saved. A save is only
+    /// restored onto a level with the same checksum.
+    pub checksum: String,
+    pub moves: usize,
+    /// The moves played before the level was saved. Saves from before
+    /// moves were kept don't have them.
+    #[serde(default)]
+    pub history: Vec<Move>,
+    pub nodes: Vec<Node>,
+    pub saved_at: u64,
+}
+
+impl SavedLevel {
+    pub fn new(level: Level) -> SavedLevel {
+        SavedLevel {
+            info: level.info,
+            checksum: level.checksum,
+            moves: level.moves,
+            history: level.history,
+            nodes: level.nodes,
+            saved_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap()
+                .as_secs(),
+        }
+    }
+
+    fn file(saves_dir: &str, source: &LevelSource) -> String {
+        format!("{saves_dir}/{}.json", source.key())
+    }
+
+    /// Writes the save to `saves_dir`, replacing any earlier save of
+    /// the same level.
+    pub fn write(&self, saves_dir: &str) -> Result<(), String> {
+        fs::create_dir_all(saves_dir).map_err(|e| e.to_string())?;
+        let content = serde_json::to_string(self).map_err(|e| e.to_string())?;
+        fs::write(SavedLevel::file(saves_dir, &self.info.source), content)
+            .map_err(|e| e.to_string())
+    }
+
+    /// The most recently saved level in `saves_dir`, if any. Saves
+    /// that can't be read are ignored.
+    pub fn latest(saves_dir: &str) -> Option<SavedLevel> {
+        fs::read_dir(saves_dir)
+            .ok()?
+            .filter_map(|f| fs::read_to_string(f.ok()?.path()).ok())
+            .filter_map(|content| serde_json::from_str::<SavedLevel>(&content).ok())
+            .max_by_key(|save| save.saved_at)
+    }
+
+    /// Deletes the save of the level from `source`, if there is one.
+    pub fn remove(saves_dir: &str, source: &LevelSource) {
+        fs::remove_file(SavedLevel::file(saves_dir, source)).ok();
+    }
+
+    /// Restores the saved state onto `level`, which must be freshly
+    /// loaded from the same source. Returns `false` without changing
+    /// `level` if the level has changed since it was saved.
+    pub fn restore(self, level: &mut Level) -> bool {
+        if self.checksum != level.checksum {
+            return false;
+        }
+        level.player_index = self
+            .nodes
+            .iter()
+            .position(|n| matches!(n.node_type, NodeType::Player(_)));
+        level.nodes = self.nodes;
+        level.moves = self.moves;
+        level.history = self.history;
+        true
+    }
+}
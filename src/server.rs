@@ -0,0 +1,333 @@
+use crate::{
+    level::{checksum, Level, LevelSource},
+    moves::Move,
+    publish::{listed_level, listed_levels, LEVELS_DIR},
+    repository::{
+        LevelReport, LevelUpload, ListingQuery, RepositoryResponse, SubmissionError,
+        LISTING_VERSION, RESULTS_PATH, UPLOAD_PATH,
+    },
+};
+use hyper::{
+    body::HttpBody,
+    header::{HeaderValue, CONTENT_TYPE, ETAG, IF_NONE_MATCH},
+    service::{make_service_fn, service_fn},
+    Body, Method, Request, Response, StatusCode,
+};
+use percent_encoding::percent_decode_str;
+use reqwest::Url;
+use std::{
+    convert::Infallible,
+    fs::OpenOptions,
+    io::{ErrorKind, Write},
+    net::{SocketAddr, TcpListener},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+/// The file reported results are added to, in the served directory.
+pub const RESULTS_FILE: &str = "l1t-results.jsonl";
+
+/// The largest upload or report that's read.
+const MAX_SUBMISSION: usize = 1024 * 1024;
+
+/// A repository server for a directory of `.l1t` files, so levels can be
+/// shared without building a repository first. The listing is made from
+/// the directory on every request, so levels that are added or changed
+/// are served straight away, and levels that aren't valid are left out.
+pub struct LevelServer {
+    dir: PathBuf,
+    listener: TcpListener,
+    uploads: bool,
+}
+
+/// What every request to a server is handled with.
+struct Site {
+    dir: PathBuf,
+    uploads: bool,
+}
+
+impl LevelServer {
+    /// Listens on `addr` for requests for the levels in `dir`.
+    pub fn bind(dir: &Path, addr: &str) -> Result<LevelServer, String> {
+        if !dir.is_dir() {
+            return Err(format!("{} isn't a directory", dir.display()));
+        }
+        let listener = TcpListener::bind(addr).map_err(|e| format!("{addr}: {e}"))?;
+        Ok(LevelServer {
+            dir: dir.to_path_buf(),
+            listener,
+            uploads: false,
+        })
+    }
+
+    /// Accepts levels uploaded to the server, which are added to its
+    /// directory if they can be won, and results reported to it, which
+    /// are added to [`RESULTS_FILE`].
+    pub fn accept_uploads(mut self) -> LevelServer {
+        self.uploads = true;
+        self
+    }
+
+    pub fn local_addr(&self) -> Result<SocketAddr, String> {
+        self.listener.local_addr().map_err(|e| e.to_string())
+    }
+
+    /// Serves requests until the server fails.
+    pub async fn run(self) -> Result<(), String> {
+        let site = Arc::new(Site {
+            dir: self.dir,
+            uploads: self.uploads,
+        });
+        let make_service = make_service_fn(move |_| {
+            let site = site.clone();
+            async move {
+                Ok::<_, Infallible>(service_fn(move |request| {
+                    let site = site.clone();
+                    async move { Ok::<_, Infallible>(respond(&site, request).await) }
+                }))
+            }
+        });
+        hyper::Server::from_tcp(self.listener)
+            .map_err(|e| e.to_string())?
+            .serve(make_service)
+            .await
+            .map_err(|e| e.to_string())
+    }
+}
+
+/// Replies to `request` with the listing at `/l1t` or a level under
+/// `/levels/`, or takes an upload or report posted to the server.
+async fn respond(site: &Site, request: Request<Body>) -> Response<Body> {
+    if request.method() == Method::POST {
+        return submission(site, request).await;
+    }
+    if request.method() != Method::GET && request.method() != Method::HEAD {
+        return status(StatusCode::METHOD_NOT_ALLOWED);
+    }
+    let dir = &site.dir;
+    let path = request.uri().path();
+    let file = path
+        .strip_prefix('/')
+        .and_then(|p| p.strip_prefix(LEVELS_DIR))
+        .and_then(|p| p.strip_prefix('/'));
+    let reply = match (path, file) {
+        ("/l1t", _) => listing(dir, &request),
+        (_, Some(file)) => level(dir, file),
+        _ => Err(StatusCode::NOT_FOUND),
+    };
+    let (content, content_type) = match reply {
+        Ok(reply) => reply,
+        Err(code) => return status(code),
+    };
+    let etag = format!("\"{}\"", checksum(&content));
+    let unchanged = request
+        .headers()
+        .get(IF_NONE_MATCH)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.split(',').any(|tag| tag.trim() == etag));
+    let mut response = if unchanged {
+        status(StatusCode::NOT_MODIFIED)
+    } else if request.method() == Method::HEAD {
+        Response::new(Body::empty())
+    } else {
+        Response::new(Body::from(content))
+    };
+    let headers = response.headers_mut();
+    headers.insert(CONTENT_TYPE, HeaderValue::from_static(content_type));
+    if let Ok(etag) = HeaderValue::from_str(&etag) {
+        headers.insert(ETAG, etag);
+    }
+    response
+}
+
+/// The listing of every valid level in `dir`, narrowed down by the `q`
+/// and `tag` query parameters like a search.
+fn listing(dir: &Path, request: &Request<Body>) -> Result<(String, &'static str), StatusCode> {
+    let url = Url::parse("http://localhost")
+        .and_then(|base| base.join(&request.uri().to_string()))
+        .map_err(|_| StatusCode::BAD_REQUEST)?;
+    let mut query = ListingQuery::default();
+    for (name, value) in url.query_pairs() {
+        match name.as_ref() {
+            "q" => query.text = value.to_string(),
+            "tag" => query.tags.push(value.to_string()),
+            _ => (),
+        }
+    }
+    let (levels, _) = listed_levels(dir).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let listing = RepositoryResponse {
+        version: LISTING_VERSION,
+        levels: levels
+            .into_iter()
+            .map(|(level, _)| level)
+            .filter(|level| query.matches(&level.clone().info(LevelSource::Url(String::new()))))
+            .collect(),
+        next: None,
+    };
+    let content =
+        serde_json::to_string_pretty(&listing).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    Ok((content, "application/json"))
+}
+
+/// The options and grid of the level file named `file` in `dir`, which
+/// has to be a valid `.l1t` file directly inside of it.
+fn level(dir: &Path, file: &str) -> Result<(String, &'static str), StatusCode> {
+    let file = percent_decode_str(file)
+        .decode_utf8()
+        .map_err(|_| StatusCode::NOT_FOUND)?;
+    let is_file_name =
+        !file.contains(['/', '\\']) && !file.starts_with('.') && file.ends_with(".l1t");
+    if !is_file_name {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    let path = dir.join(file.as_ref());
+    if !path.is_file() {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    let (_, grid) = listed_level(&path).map_err(|_| StatusCode::NOT_FOUND)?;
+    Ok((grid, "text/plain; charset=utf-8"))
+}
+
+/// Takes a level uploaded to `/levels` or a result reported to
+/// `/results`, replying with JSON.
+async fn submission(site: &Site, request: Request<Body>) -> Response<Body> {
+    let path = request.uri().path().trim_start_matches('/');
+    let is_upload = match path {
+        UPLOAD_PATH => true,
+        RESULTS_PATH => false,
+        _ => return status(StatusCode::NOT_FOUND),
+    };
+    if !site.uploads {
+        return status(StatusCode::METHOD_NOT_ALLOWED);
+    }
+    let reply = match read_body(request.into_body()).await {
+        Ok(body) if is_upload => upload(&site.dir, &body),
+        Ok(body) => report(&site.dir, &body),
+        Err(e) => Err(e),
+    };
+    let (code, content) = match reply {
+        Ok(reply) => reply,
+        Err((code, error)) => (
+            code,
+            serde_json::to_string(&SubmissionError { error }).unwrap_or_default(),
+        ),
+    };
+    let mut response = Response::new(Body::from(content));
+    *response.status_mut() = code;
+    response
+        .headers_mut()
+        .insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+    response
+}
+
+type Submission = Result<(StatusCode, String), (StatusCode, String)>;
+
+async fn read_body(mut body: Body) -> Result<String, (StatusCode, String)> {
+    let mut content = vec![];
+    while let Some(chunk) = body.data().await {
+        let chunk = chunk.map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+        if content.len() + chunk.len() > MAX_SUBMISSION {
+            return Err((StatusCode::PAYLOAD_TOO_LARGE, "it's too large".to_string()));
+        }
+        content.extend_from_slice(&chunk);
+    }
+    String::from_utf8(content).map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))
+}
+
+/// Adds an uploaded level to `dir` if it's valid and its solution wins
+/// it, replying with its entry in the listing. Levels are named after
+/// their name, and never replace a level that's already there.
+fn upload(dir: &Path, body: &str) -> Submission {
+    let upload: LevelUpload =
+        serde_json::from_str(body).map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+    let rejected = |e: String| (StatusCode::UNPROCESSABLE_ENTITY, e);
+    let mut level = Level::parse(&upload.level, LevelSource::Url(String::new()))
+        .map_err(|e| rejected(e.to_string()))?;
+    let solution = Move::parse(&upload.solution).map_err(rejected)?;
+    if !level.replay(&solution) {
+        return Err(rejected("the solution doesn't win the level".to_string()));
+    }
+    let path = dir.join(format!("{}.l1t", file_stem(&level.info.name)));
+    let internal = |e: String| (StatusCode::INTERNAL_SERVER_ERROR, e);
+    let mut file = match OpenOptions::new().write(true).create_new(true).open(&path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == ErrorKind::AlreadyExists => {
+            return Err((
+                StatusCode::CONFLICT,
+                format!("there's already a level called {}", level.info.name),
+            ))
+        }
+        Err(e) => return Err(internal(e.to_string())),
+    };
+    file.write_all(upload.level.as_bytes())
+        .map_err(|e| internal(e.to_string()))?;
+    let (info, _) = listed_level(&path).map_err(internal)?;
+    let content = serde_json::to_string(&info).map_err(|e| internal(e.to_string()))?;
+    Ok((StatusCode::CREATED, content))
+}
+
+/// Adds a reported result to the [`RESULTS_FILE`] in `dir` if it's for
+/// one of its levels, as it is now.
+fn report(dir: &Path, body: &str) -> Submission {
+    let report: LevelReport =
+        serde_json::from_str(body).map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+    let unknown = || {
+        (
+            StatusCode::UNPROCESSABLE_ENTITY,
+            format!("there's no level `{}`", report.level),
+        )
+    };
+    let file = report
+        .level
+        .strip_prefix(LEVELS_DIR)
+        .and_then(|p| p.strip_prefix('/'))
+        .ok_or_else(unknown)?;
+    let (grid, _) = level(dir, file).map_err(|_| unknown())?;
+    if report
+        .hash
+        .as_ref()
+        .is_some_and(|hash| !hash.eq_ignore_ascii_case(&checksum(&grid)))
+    {
+        return Err((
+            StatusCode::CONFLICT,
+            "the level has changed since it was played".to_string(),
+        ));
+    }
+    let internal = |e: std::io::Error| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string());
+    let line = serde_json::to_string(&report)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(dir.join(RESULTS_FILE))
+        .and_then(|mut file| writeln!(file, "{line}"))
+        .map_err(internal)?;
+    Ok((StatusCode::NO_CONTENT, String::new()))
+}
+
+/// The name of the file a level called `name` is uploaded to, made of
+/// its letters and numbers.
+fn file_stem(name: &str) -> String {
+    let stem: String = name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_lowercase()
+            } else {
+                ' '
+            }
+        })
+        .collect();
+    let stem = stem.split_whitespace().collect::<Vec<_>>().join("-");
+    if stem.is_empty() {
+        "level".to_string()
+    } else {
+        stem
+    }
+}
+
+fn status(code: StatusCode) -> Response<Body> {
+    let mut response = Response::new(Body::empty());
+    *response.status_mut() = code;
+    response
+}
@@ -0,0 +1,89 @@
+use crate::theme::Theme;
+use serde::{Deserialize, Serialize};
+use std::{fs, path, sync::RwLock};
+
+static SETTINGS: RwLock<Option<Settings>> = RwLock::new(None);
+
+/// The player's preferences, kept in `settings.toml` in the config
+/// directory. Settings left out of the file keep their defaults.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    /// How long to wait, in milliseconds, after a level is won or lost
+    /// before showing the result, so the final laser beams can be seen.
+    pub animation_delay: u64,
+    /// Ask before quitting a level.
+    pub confirm_on_quit: bool,
+    /// The name of the color theme to draw with, see `Theme`.
+    pub theme: String,
+    /// Only draw ASCII characters, for terminals and fonts without box
+    /// drawing characters.
+    pub ascii_only: bool,
+    /// Go straight to the next core level after winning one instead of
+    /// returning to the main menu.
+    pub auto_advance: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            animation_delay: 500,
+            confirm_on_quit: true,
+            theme: Theme::DEFAULT.to_string(),
+            ascii_only: false,
+            auto_advance: true,
+        }
+    }
+}
+
+impl Settings {
+    /// The longest `animation_delay` allowed.
+    pub const MAX_ANIMATION_DELAY: u64 = 5000;
+
+    /// Reads `settings.toml` from `config_dir`, writing the default
+    /// settings to it first if it does not exist.
+    pub fn read(config_dir: &str) -> Result<Settings, String> {
+        let file = config_dir.to_string() + "/settings.toml";
+        if !path::Path::new(&file).exists() {
+            Settings::default().write(config_dir)?;
+        }
+        let file_content = fs::read_to_string(&file).unwrap_or_default();
+        Settings::parse(&file_content).map_err(|e| format!("{file}: {e}"))
+    }
+
+    pub fn parse(content: &str) -> Result<Settings, String> {
+        let settings: Settings = toml::from_str(content).map_err(|e| e.message().to_string())?;
+        if settings.animation_delay > Settings::MAX_ANIMATION_DELAY {
+            return Err(format!(
+                "animation_delay can't be more than {}",
+                Settings::MAX_ANIMATION_DELAY
+            ));
+        }
+        Ok(settings)
+    }
+
+    /// Writes the settings to `settings.toml` in `config_dir`.
+    pub fn write(&self, config_dir: &str) -> Result<(), String> {
+        fs::create_dir_all(config_dir).map_err(|e| e.to_string())?;
+        let content = toml::to_string(self).map_err(|e| e.to_string())?;
+        fs::write(config_dir.to_string() + "/settings.toml", content).map_err(|e| e.to_string())
+    }
+
+    /// Makes these the settings returned by `Settings::active`. Unlike the
+    /// keymap, settings can be changed while playing, so this can be
+    /// called more than once.
+    pub fn install(self) {
+        if let Ok(mut settings) = SETTINGS.write() {
+            *settings = Some(self);
+        }
+    }
+
+    /// The installed settings, or the defaults if none were installed.
+    pub fn active() -> Settings {
+        SETTINGS
+            .read()
+            .ok()
+            .and_then(|s| s.clone())
+            .unwrap_or_default()
+    }
+}
@@ -0,0 +1,511 @@
+use crossterm::style::{Attribute, Color, ContentStyle, Stylize};
+use std::{collections::BTreeMap, env, fs, sync::RwLock};
+
+static THEME: RwLock<Option<Theme>> = RwLock::new(None);
+
+/// The colors everything in the game is drawn with, by what they're used
+/// for rather than what they look like.
+///
+/// Besides the built in themes, custom themes can be made by adding a
+/// `<name>.toml` file to the `themes` folder in the config directory:
+///
+/// ```toml
+/// # The theme to start from, `dark` if left out.
+/// base = "dark"
+/// statue_lit = "#ffd700"
+/// laser_on = "red"
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Theme {
+    /// Menu text and borders.
+    pub text: Color,
+    /// The background of the selected menu item and of titles.
+    pub highlight: Color,
+    /// The text of the selected menu item and of titles.
+    pub highlight_text: Color,
+    /// Letters drawn on top of colored blocks.
+    pub contrast: Color,
+    /// The color of the logo letters on the main menu.
+    pub logo: Color,
+    pub wall: Color,
+    pub player: Color,
+    /// The color of the player's arrow once they've been shot.
+    pub player_dead: Color,
+    pub block: Color,
+    pub toggle_block: Color,
+    /// Switches that are on and buttons that are pressed.
+    pub on: Color,
+    /// Switches that are off and buttons that aren't pressed.
+    pub off: Color,
+    pub mirror: Color,
+    pub moveable_mirror: Color,
+    pub laser_on: Color,
+    pub laser_off: Color,
+    pub beam: Color,
+    pub statue_lit: Color,
+    pub statue_unlit: Color,
+    pub zapper: Color,
+    /// The terminal can't show colors, so states are shown with letters
+    /// and reverse video instead. Every color is `Color::Reset`.
+    pub monochrome: bool,
+}
+
+const fn rgb(r: u8, g: u8, b: u8) -> Color {
+    Color::Rgb { r, g, b }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme::dark()
+    }
+}
+
+impl Theme {
+    /// The name of the theme used when none is chosen.
+    pub const DEFAULT: &'static str = "dark";
+
+    /// The names of the themes that come with `l1t`.
+    pub const BUILT_IN: [&'static str; 4] = ["dark", "light", "high-contrast", "deuteranopia"];
+
+    /// The names of the colors a custom theme can set.
+    const ROLES: [&'static str; 20] = [
+        "text",
+        "highlight",
+        "highlight_text",
+        "contrast",
+        "logo",
+        "wall",
+        "player",
+        "player_dead",
+        "block",
+        "toggle_block",
+        "on",
+        "off",
+        "mirror",
+        "moveable_mirror",
+        "laser_on",
+        "laser_off",
+        "beam",
+        "statue_lit",
+        "statue_unlit",
+        "zapper",
+    ];
+
+    /// For terminals with a dark background.
+    pub fn dark() -> Theme {
+        Theme {
+            text: Color::White,
+            highlight: Color::White,
+            highlight_text: Color::Black,
+            contrast: Color::Black,
+            logo: Color::Green,
+            wall: Color::White,
+            player: Color::Green,
+            player_dead: rgb(255, 0, 0),
+            block: Color::Grey,
+            toggle_block: Color::Magenta,
+            on: Color::Yellow,
+            off: Color::Red,
+            mirror: Color::White,
+            moveable_mirror: Color::White,
+            laser_on: rgb(255, 0, 0),
+            laser_off: rgb(100, 0, 0),
+            beam: rgb(255, 0, 0),
+            statue_lit: rgb(255, 255, 0),
+            statue_unlit: rgb(100, 100, 0),
+            zapper: Color::Yellow,
+            monochrome: false,
+        }
+    }
+
+    /// For terminals with a light background.
+    pub fn light() -> Theme {
+        Theme {
+            text: Color::Black,
+            highlight: Color::Black,
+            highlight_text: Color::White,
+            contrast: Color::Black,
+            logo: rgb(0, 140, 0),
+            wall: rgb(90, 90, 90),
+            player: rgb(0, 170, 0),
+            player_dead: rgb(200, 0, 0),
+            block: rgb(170, 170, 170),
+            toggle_block: rgb(170, 0, 170),
+            on: rgb(230, 180, 0),
+            off: rgb(220, 60, 60),
+            mirror: Color::Black,
+            moveable_mirror: rgb(200, 200, 200),
+            laser_on: rgb(220, 0, 0),
+            laser_off: rgb(240, 170, 170),
+            beam: rgb(220, 0, 0),
+            statue_lit: rgb(240, 200, 0),
+            statue_unlit: rgb(150, 130, 60),
+            zapper: rgb(200, 150, 0),
+            monochrome: false,
+        }
+    }
+
+    /// Only fully saturated colors, with dim states replaced by grey so
+    /// they stand apart from their lit counterparts.
+    pub fn high_contrast() -> Theme {
+        Theme {
+            text: rgb(255, 255, 255),
+            highlight: rgb(255, 255, 0),
+            highlight_text: rgb(0, 0, 0),
+            contrast: rgb(0, 0, 0),
+            logo: rgb(0, 255, 0),
+            wall: rgb(255, 255, 255),
+            player: rgb(0, 255, 0),
+            player_dead: rgb(255, 0, 0),
+            block: rgb(0, 170, 255),
+            toggle_block: rgb(255, 0, 255),
+            on: rgb(255, 255, 0),
+            off: rgb(128, 128, 128),
+            mirror: rgb(255, 255, 255),
+            moveable_mirror: rgb(255, 255, 255),
+            laser_on: rgb(255, 0, 0),
+            laser_off: rgb(128, 128, 128),
+            beam: rgb(255, 0, 0),
+            statue_lit: rgb(255, 255, 0),
+            statue_unlit: rgb(128, 128, 128),
+            zapper: rgb(255, 128, 0),
+            monochrome: false,
+        }
+    }
+
+    /// Avoids telling things apart by red and green, using the blue and
+    /// orange colors of the Okabe-Ito palette instead.
+    pub fn deuteranopia() -> Theme {
+        Theme {
+            text: Color::White,
+            highlight: Color::White,
+            highlight_text: Color::Black,
+            contrast: Color::Black,
+            logo: rgb(86, 180, 233),
+            wall: Color::White,
+            player: rgb(86, 180, 233),
+            player_dead: rgb(213, 94, 0),
+            block: Color::Grey,
+            toggle_block: rgb(204, 121, 167),
+            on: rgb(240, 228, 66),
+            off: rgb(0, 114, 178),
+            mirror: Color::White,
+            moveable_mirror: Color::White,
+            laser_on: rgb(213, 94, 0),
+            laser_off: rgb(90, 40, 0),
+            beam: rgb(230, 159, 0),
+            statue_lit: rgb(240, 228, 66),
+            statue_unlit: rgb(0, 114, 178),
+            zapper: rgb(204, 121, 167),
+            monochrome: false,
+        }
+    }
+
+    fn built_in(name: &str) -> Option<Theme> {
+        match name {
+            "dark" => Some(Theme::dark()),
+            "light" => Some(Theme::light()),
+            "high-contrast" => Some(Theme::high_contrast()),
+            "deuteranopia" => Some(Theme::deuteranopia()),
+            _ => None,
+        }
+    }
+
+    /// The names of every theme that can be chosen: the built in themes
+    /// followed by the custom themes in `config_dir`, sorted.
+    pub fn names(config_dir: &str) -> Vec<String> {
+        let mut custom: Vec<String> = fs::read_dir(config_dir.to_string() + "/themes")
+            .map(|dir| {
+                dir.filter_map(|f| f.ok())
+                    .filter_map(|f| {
+                        f.file_name()
+                            .to_str()?
+                            .strip_suffix(".toml")
+                            .map(|n| n.to_string())
+                    })
+                    .filter(|n| !Theme::BUILT_IN.contains(&n.as_str()))
+                    .collect()
+            })
+            .unwrap_or_default();
+        custom.sort_by(|a, b| human_sort::compare(a, b));
+        let mut names: Vec<String> = Theme::BUILT_IN.iter().map(|n| n.to_string()).collect();
+        names.append(&mut custom);
+        names
+    }
+
+    /// Loads the theme called `name`, either a built in theme or one from
+    /// the `themes` folder in `config_dir`.
+    pub fn load(name: &str, config_dir: &str) -> Result<Theme, String> {
+        if let Some(theme) = Theme::built_in(name) {
+            return Ok(theme);
+        }
+        let file = format!("{config_dir}/themes/{name}.toml");
+        let content = fs::read_to_string(&file).map_err(|_| {
+            format!(
+                "unknown theme `{name}`, expected one of: {}",
+                Theme::names(config_dir).join(", ")
+            )
+        })?;
+        Theme::parse(&content).map_err(|e| format!("{file}: {e}"))
+    }
+
+    /// Parses a custom theme file, which maps color roles to colors on
+    /// top of a built in `base` theme.
+    pub fn parse(content: &str) -> Result<Theme, String> {
+        let mut file: BTreeMap<String, String> =
+            toml::from_str(content).map_err(|e| e.message().to_string())?;
+        let base = file
+            .remove("base")
+            .unwrap_or_else(|| Theme::DEFAULT.to_string());
+        let mut theme = Theme::built_in(&base).ok_or(format!(
+            "unknown base theme `{base}`, expected one of: {}",
+            Theme::BUILT_IN.join(", ")
+        ))?;
+        for (role, color) in file {
+            let color = parse_color(&color).ok_or(format!("invalid color `{color}` for {role}"))?;
+            *theme.role_mut(&role).ok_or(format!(
+                "unknown role `{role}`, expected one of: {}",
+                Theme::ROLES.join(", ")
+            ))? = color;
+        }
+        Ok(theme)
+    }
+
+    fn role_mut(&mut self, role: &str) -> Option<&mut Color> {
+        Some(match role {
+            "text" => &mut self.text,
+            "highlight" => &mut self.highlight,
+            "highlight_text" => &mut self.highlight_text,
+            "contrast" => &mut self.contrast,
+            "logo" => &mut self.logo,
+            "wall" => &mut self.wall,
+            "player" => &mut self.player,
+            "player_dead" => &mut self.player_dead,
+            "block" => &mut self.block,
+            "toggle_block" => &mut self.toggle_block,
+            "on" => &mut self.on,
+            "off" => &mut self.off,
+            "mirror" => &mut self.mirror,
+            "moveable_mirror" => &mut self.moveable_mirror,
+            "laser_on" => &mut self.laser_on,
+            "laser_off" => &mut self.laser_off,
+            "beam" => &mut self.beam,
+            "statue_lit" => &mut self.statue_lit,
+            "statue_unlit" => &mut self.statue_unlit,
+            "zapper" => &mut self.zapper,
+            _ => return None,
+        })
+    }
+
+    /// Converts every color to one the terminal can show.
+    pub fn adapted(mut self, support: ColorSupport) -> Theme {
+        for role in Theme::ROLES {
+            if let Some(color) = self.role_mut(role) {
+                *color = match support {
+                    ColorSupport::None => Color::Reset,
+                    ColorSupport::Basic => to_basic(*color),
+                    ColorSupport::Ansi256 => to_ansi256(*color),
+                    ColorSupport::TrueColor => *color,
+                };
+            }
+        }
+        self.monochrome = support == ColorSupport::None;
+        self
+    }
+
+    /// The style of titles, prompts and the selected menu item.
+    pub fn highlight(&self) -> ContentStyle {
+        if self.monochrome {
+            ContentStyle::new().reverse()
+        } else {
+            ContentStyle::new()
+                .with(self.highlight_text)
+                .on(self.highlight)
+        }
+    }
+
+    /// The style of a menu item, highlighted if it's `selected`.
+    pub fn item(&self, selected: bool) -> ContentStyle {
+        if selected {
+            self.highlight()
+        } else {
+            ContentStyle::new()
+                .with(self.text)
+                .on(Color::Reset)
+                .attribute(Attribute::NoReverse)
+        }
+    }
+
+    /// Makes this the theme returned by `Theme::active`, adapted to the
+    /// colors the terminal can show.
+    pub fn install(self) {
+        if let Ok(mut theme) = THEME.write() {
+            *theme = Some(self.adapted(ColorSupport::detect()));
+        }
+    }
+
+    /// The installed theme, or the default theme if none was installed.
+    pub fn active() -> Theme {
+        THEME
+            .read()
+            .ok()
+            .and_then(|t| t.clone())
+            .unwrap_or_else(|| Theme::default().adapted(ColorSupport::detect()))
+    }
+}
+
+/// How many colors the terminal can show.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorSupport {
+    /// No colors at all, either because the terminal can't show them or
+    /// because `NO_COLOR` is set.
+    None,
+    /// The 16 standard ANSI colors.
+    Basic,
+    /// The 256 color xterm palette.
+    Ansi256,
+    /// Any RGB color.
+    TrueColor,
+}
+
+impl ColorSupport {
+    /// Guesses what the terminal supports from the `NO_COLOR`, `COLORTERM`
+    /// and `TERM` environment variables.
+    pub fn detect() -> ColorSupport {
+        if env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty()) {
+            return ColorSupport::None;
+        }
+        if matches!(
+            env::var("COLORTERM").as_deref(),
+            Ok("truecolor") | Ok("24bit")
+        ) {
+            return ColorSupport::TrueColor;
+        }
+        let term = env::var("TERM").unwrap_or_default();
+        if term == "dumb" {
+            ColorSupport::None
+        } else if term.contains("direct") || term.contains("truecolor") {
+            ColorSupport::TrueColor
+        } else if term.contains("256color") {
+            ColorSupport::Ansi256
+        } else if term.is_empty() && cfg!(windows) {
+            // Windows terminals don't set `TERM` but support RGB colors.
+            ColorSupport::TrueColor
+        } else {
+            ColorSupport::Basic
+        }
+    }
+}
+
+/// The colors of the 16 standard ANSI colors in xterm, in order.
+const BASIC: [(Color, (u8, u8, u8)); 16] = [
+    (Color::Black, (0, 0, 0)),
+    (Color::DarkRed, (205, 0, 0)),
+    (Color::DarkGreen, (0, 205, 0)),
+    (Color::DarkYellow, (205, 205, 0)),
+    (Color::DarkBlue, (0, 0, 238)),
+    (Color::DarkMagenta, (205, 0, 205)),
+    (Color::DarkCyan, (0, 205, 205)),
+    (Color::Grey, (229, 229, 229)),
+    (Color::DarkGrey, (127, 127, 127)),
+    (Color::Red, (255, 0, 0)),
+    (Color::Green, (0, 255, 0)),
+    (Color::Yellow, (255, 255, 0)),
+    (Color::Blue, (92, 92, 255)),
+    (Color::Magenta, (255, 0, 255)),
+    (Color::Cyan, (0, 255, 255)),
+    (Color::White, (255, 255, 255)),
+];
+
+/// The levels of each channel in the 6x6x6 color cube of the 256 color
+/// palette.
+const CUBE: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+fn distance(a: (u8, u8, u8), b: (u8, u8, u8)) -> u32 {
+    let d = |x: u8, y: u8| (x as i32 - y as i32).pow(2) as u32;
+    d(a.0, b.0) + d(a.1, b.1) + d(a.2, b.2)
+}
+
+/// The RGB value of an ANSI color, or `None` for named colors, which
+/// every terminal can show already.
+fn ansi_to_rgb(color: Color) -> Option<(u8, u8, u8)> {
+    match color {
+        Color::Rgb { r, g, b } => Some((r, g, b)),
+        Color::AnsiValue(n @ 0..=15) => Some(BASIC[n as usize].1),
+        Color::AnsiValue(n @ 16..=231) => {
+            let n = n - 16;
+            Some((
+                CUBE[n as usize / 36],
+                CUBE[n as usize / 6 % 6],
+                CUBE[n as usize % 6],
+            ))
+        }
+        Color::AnsiValue(n) => {
+            let level = 8 + (n - 232) * 10;
+            Some((level, level, level))
+        }
+        _ => None,
+    }
+}
+
+/// The closest of the 16 standard colors. Colorful colors are only
+/// matched to other colorful colors, so that dim colors don't turn
+/// black or grey and stay apart from their bright counterparts.
+fn to_basic(color: Color) -> Color {
+    let Some(rgb) = ansi_to_rgb(color) else {
+        return color;
+    };
+    let chroma = |(r, g, b): (u8, u8, u8)| r.max(g).max(b) - r.min(g).min(b);
+    let colorful = chroma(rgb) >= 80;
+    BASIC
+        .iter()
+        .filter(|(_, c)| !colorful || chroma(*c) > 0)
+        .min_by_key(|(_, c)| distance(rgb, *c))
+        .map(|(color, _)| *color)
+        .unwrap_or(color)
+}
+
+/// The closest color in the 256 color palette.
+fn to_ansi256(color: Color) -> Color {
+    let Some(rgb) = ansi_to_rgb(color) else {
+        return color;
+    };
+    let nearest_level = |v: u8| {
+        (0..CUBE.len())
+            .min_by_key(|i| (CUBE[*i] as i32 - v as i32).abs())
+            .unwrap_or(0)
+    };
+    let (r, g, b) = (
+        nearest_level(rgb.0),
+        nearest_level(rgb.1),
+        nearest_level(rgb.2),
+    );
+    let cube = (16 + 36 * r + 6 * g + b) as u8;
+    let average = (rgb.0 as u32 + rgb.1 as u32 + rgb.2 as u32) / 3;
+    let grey = 232 + (average.saturating_sub(3) / 10).min(23) as u8;
+    [cube, grey]
+        .into_iter()
+        .min_by_key(|n| distance(rgb, ansi_to_rgb(Color::AnsiValue(*n)).unwrap_or(rgb)))
+        .map(Color::AnsiValue)
+        .unwrap_or(color)
+}
+
+/// Parses a color written as `#rrggbb`, an ANSI color number from 0 to
+/// 255, or a name like `red` or `dark_grey`.
+fn parse_color(color: &str) -> Option<Color> {
+    let color = color.trim();
+    if let Some(hex) = color.strip_prefix('#') {
+        if hex.len() != 6 {
+            return None;
+        }
+        let channel = |i: usize| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok();
+        return Some(rgb(channel(0)?, channel(2)?, channel(4)?));
+    }
+    if let Ok(n) = color.parse::<u8>() {
+        return Some(Color::AnsiValue(n));
+    }
+    match Color::try_from(color.to_lowercase().as_str()) {
+        Ok(Color::Reset) | Err(_) => None,
+        Ok(c) => Some(c),
+    }
+}
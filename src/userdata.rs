@@ -1,10 +1,17 @@
 use crate::{
-    level::{LevelInfo, LevelSource},
+    cache::Cache,
+    level::{Level, LevelInfo, LevelSource},
+    pack::Pack,
+    paths::Paths,
     repository::Repository,
+    save::SavedLevel,
+    settings::Settings,
+    theme::Theme,
 };
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::time::{SystemTime, UNIX_EPOCH};
-use std::{fs, path};
+use std::{fs, io::Write, path};
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct CompletedRepoLevel {
@@ -13,135 +20,617 @@ pub struct CompletedRepoLevel {
     pub author: String,
     pub description: String,
     pub completed_at: u64,
+    /// The SHA-256 hash of the level file that was completed. Levels
+    /// completed before hashes were recorded don't have one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hash: Option<String>,
+    /// The commit the level was played at, for levels from git
+    /// repositories.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub commit: Option<String>,
+}
+
+impl CompletedRepoLevel {
+    /// Whether this is a completion of the level at `url`. When both have
+    /// a hash, only a completion of the exact same level content counts.
+    /// Levels aren't matched by name and author, as different levels in
+    /// different repositories often share them.
+    pub fn matches(&self, url: &str, hash: Option<&str>) -> bool {
+        match (&self.hash, hash) {
+            (Some(a), Some(b)) => a.eq_ignore_ascii_case(b),
+            _ => self.url == url,
+        }
+    }
+}
+
+/// Statistics about how the player has played, kept per profile.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct Stats {
+    pub levels_won: u64,
+    pub levels_lost: u64,
+    pub total_moves: u64,
+    /// The fewest moves each level has been won in, keyed by
+    /// `LevelSource::key`.
+    pub best_moves: BTreeMap<String, usize>,
 }
 
 pub struct UserData {
+    paths: Paths,
     file: String,
+    saves_dir: String,
+    /// The name of the profile this data belongs to.
+    pub profile: String,
     pub completed_core_levels: Vec<usize>,
     pub completed_levels: Vec<CompletedRepoLevel>,
+    /// The levels completed in each pack, keyed by `Pack::key`.
+    pub completed_pack_levels: BTreeMap<String, Vec<usize>>,
+    pub stats: Stats,
+    pub settings: Settings,
     pub repositories: Vec<Repository>,
+    /// Downloaded repository listings and levels.
+    pub cache: Cache,
+    /// Problems found while reading the user's data that were recovered
+    /// from, to be shown to the player.
+    pub warnings: Vec<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct SerializedUserData {
-    file: String,
+    pub version: u32,
     pub completed_core_levels: Vec<usize>,
     pub completed_levels: Vec<CompletedRepoLevel>,
+    pub completed_pack_levels: BTreeMap<String, Vec<usize>>,
+    pub stats: Stats,
 }
 
-impl UserData {
-    fn read_repositories(home_dir: String) -> Result<Vec<Repository>, String> {
-        let file = home_dir.to_string() + "/.l1t/repositories.l1t_conf";
-        if !path::Path::new(&file).exists() {
-            fs::create_dir(home_dir + "/.l1t").ok();
-            if let Err(e) = fs::write(&file, "") {
-                return Err(e.to_string());
-            }
+/// Why `data.json` couldn't be parsed.
+enum DataError {
+    /// The file was written by a newer version of `l1t` and must not be
+    /// overwritten.
+    Newer(u32),
+    Corrupt,
+}
+
+impl SerializedUserData {
+    /// The current version of the `data.json` format.
+    pub const VERSION: u32 = 3;
+
+    /// Parses the contents of `data.json`, migrating data written by
+    /// older versions of `l1t` to the current format.
+    fn parse(content: &str) -> Result<SerializedUserData, DataError> {
+        let mut data: serde_json::Value =
+            serde_json::from_str(content).map_err(|_| DataError::Corrupt)?;
+        let version = match data.get("version").map(|v| v.as_u64()) {
+            Some(Some(v)) => v as u32,
+            Some(None) => return Err(DataError::Corrupt),
+            None => 0,
+        };
+        if version > SerializedUserData::VERSION {
+            return Err(DataError::Newer(version));
         }
-        let file_content = fs::read_to_string(&file).unwrap_or_default();
-        let mut repositories: Vec<Repository> = vec![];
-        for line in file_content.trim().split('\n') {
-            let parts: Vec<&str> = line.trim().split('=').collect();
-            if parts.len() < 2 {
-                continue;
+        for from in version..SerializedUserData::VERSION {
+            data = SerializedUserData::migrate(from, data).map_err(|_| DataError::Corrupt)?;
+        }
+        serde_json::from_value(data).map_err(|_| DataError::Corrupt)
+    }
+
+    /// Migrates data from version `from` to version `from + 1`.
+    fn migrate(from: u32, mut data: serde_json::Value) -> Result<serde_json::Value, String> {
+        let object = data
+            .as_object_mut()
+            .ok_or("progress must be a JSON object")?;
+        match from {
+            // Version 0 stored the path of the file in the file itself
+            // and had no version field.
+            0 => {
+                object.remove("file");
             }
-            repositories.push(Repository::new(
-                parts[0].trim().to_string(),
-                parts[1].trim().to_string(),
-            ));
-        }
-        Ok(repositories)
-    }
-
-    pub fn read(home_dir: String) -> Result<UserData, String> {
-        let file = home_dir.to_string() + "/.l1t/data.json";
-        if !path::Path::new(&file).exists() {
-            fs::create_dir(home_dir.clone() + "/.l1t").ok();
-            let data = SerializedUserData {
-                file: file.clone(),
-                completed_core_levels: vec![],
-                completed_levels: vec![],
-            };
-            let content = match serde_json::to_string(&data) {
-                Ok(c) => c,
-                Err(e) => return Err(e.to_string()),
-            };
-            if let Err(e) = fs::write(&file, content) {
-                return Err(e.to_string());
-            };
+            // Version 2 added stats.
+            1 => {
+                object.insert(
+                    "stats".to_string(),
+                    serde_json::to_value(Stats::default()).map_err(|e| e.to_string())?,
+                );
+            }
+            // Version 3 added level packs.
+            2 => {
+                object.insert("completed_pack_levels".to_string(), serde_json::json!({}));
+            }
+            _ => return Err(format!("no migration from format version {from}")),
         }
-        let file_content = fs::read_to_string(&file).unwrap_or_default();
-        let data = match serde_json::from_str::<SerializedUserData>(&file_content) {
-            Ok(d) => d,
-            Err(e) => return Err(e.to_string()),
+        object.insert("version".to_string(), (from + 1).into());
+        Ok(data)
+    }
+}
+
+impl UserData {
+    /// Reads the data of `profile`, or of the last used profile if `None`,
+    /// creating the profile if it doesn't exist yet.
+    pub fn read(paths: Paths, profile: Option<String>) -> Result<UserData, String> {
+        UserData::migrate_to_profiles(&paths.data)?;
+        let profile = UserData::profile_name(&paths, profile)?;
+        let profile_dir = UserData::profile_dir(&paths.data, &profile);
+        if let Err(e) = fs::create_dir_all(&profile_dir) {
+            return Err(e.to_string());
+        }
+        if let Err(e) = fs::write(paths.data.to_string() + "/profile", &profile) {
+            return Err(e.to_string());
+        }
+        let file = profile_dir.to_string() + "/data.json";
+        let mut warnings: Vec<String> = vec![];
+        let data = if !path::Path::new(&file).exists() {
+            None
+        } else {
+            UserData::read_data(&file, &mut warnings)?
         };
-        let repositories = UserData::read_repositories(home_dir)?;
+        let is_new = data.is_none();
+        let data = data.unwrap_or(SerializedUserData {
+            version: SerializedUserData::VERSION,
+            completed_core_levels: vec![],
+            completed_levels: vec![],
+            completed_pack_levels: BTreeMap::new(),
+            stats: Stats::default(),
+        });
+        let saves_dir = profile_dir.to_string() + "/saves";
+        let repositories = Repository::read_all(&paths.config)?;
+        let settings = UserData::profile_settings(&paths, &profile_dir)?;
+        settings.clone().install();
+        match Theme::load(&settings.theme, &paths.config) {
+            Ok(theme) => theme.install(),
+            Err(e) => warnings.push(format!("Couldn't load your theme, {e}")),
+        }
 
-        Ok(UserData {
+        let user_data = UserData {
             repositories,
+            cache: Cache::new(&paths.cache),
+            settings,
+            paths,
             file,
+            saves_dir,
+            profile,
             completed_core_levels: data.completed_core_levels,
             completed_levels: data.completed_levels,
-        })
+            completed_pack_levels: data.completed_pack_levels,
+            stats: data.stats,
+            warnings,
+        };
+        if is_new || !user_data.warnings.is_empty() {
+            user_data.write()?;
+        }
+        Ok(user_data)
     }
 
-    fn complete_core(&mut self, level: usize) -> Result<(), String> {
-        if self.completed_core_levels.iter().any(|i| *i == level) {
-            return Ok(());
+    /// Reads the settings of `profile`, or of the last used profile if
+    /// `None`, without creating the profile. Players who haven't made a
+    /// profile yet get the settings in the config directory.
+    pub fn read_settings(paths: &Paths, profile: Option<String>) -> Result<Settings, String> {
+        let profile = UserData::profile_name(paths, profile)?;
+        let profile_dir = UserData::profile_dir(&paths.data, &profile);
+        if path::Path::new(&profile_dir).is_dir() {
+            UserData::profile_settings(paths, &profile_dir)
+        } else {
+            Settings::read(&paths.config)
         }
-        self.completed_core_levels.push(level);
-        let content = match serde_json::to_string(&SerializedUserData {
-            file: self.file.clone(),
-            completed_core_levels: self.completed_core_levels.clone(),
-            completed_levels: self.completed_levels.clone(),
-        }) {
+    }
+
+    /// `profile`, or the last used profile if `None`.
+    fn profile_name(paths: &Paths, profile: Option<String>) -> Result<String, String> {
+        let profile = match profile {
+            Some(p) => p,
+            None => fs::read_to_string(paths.data.to_string() + "/profile")
+                .map(|p| p.trim().to_string())
+                .unwrap_or_default(),
+        };
+        let profile = if profile.is_empty() {
+            UserData::DEFAULT_PROFILE.to_string()
+        } else {
+            profile
+        };
+        UserData::validate_profile_name(&profile)?;
+        Ok(profile)
+    }
+
+    /// Reads `settings.toml` from `profile_dir`. Profiles start out with
+    /// the settings in the config directory, which is where every
+    /// profile's settings were kept before each had its own.
+    fn profile_settings(paths: &Paths, profile_dir: &str) -> Result<Settings, String> {
+        let file = profile_dir.to_string() + "/settings.toml";
+        let shared = paths.config.to_string() + "/settings.toml";
+        if !path::Path::new(&file).exists() && path::Path::new(&shared).exists() {
+            fs::copy(&shared, &file).map_err(|e| format!("{file}: {e}"))?;
+        }
+        Settings::read(profile_dir)
+    }
+
+    /// Reads and migrates `file`. If it is corrupted, it is set aside as
+    /// `data.json.corrupt` and the backup from the last successful write
+    /// is used instead. If neither can be read, `None` is returned so the
+    /// player starts over rather than being locked out of the game.
+    fn read_data(
+        file: &str,
+        warnings: &mut Vec<String>,
+    ) -> Result<Option<SerializedUserData>, String> {
+        let content = fs::read_to_string(file).map_err(|e| e.to_string())?;
+        match SerializedUserData::parse(&content) {
+            Ok(data) => return Ok(Some(data)),
+            Err(DataError::Newer(version)) => {
+                return Err(format!(
+                    "{file} was saved by a newer version of l1t (format version {version})"
+                ))
+            }
+            Err(DataError::Corrupt) => (),
+        };
+        fs::rename(file, file.to_string() + ".corrupt").map_err(|e| e.to_string())?;
+        let backup = fs::read_to_string(file.to_string() + ".bak")
+            .ok()
+            .and_then(|content| SerializedUserData::parse(&content).ok());
+        match backup {
+            Some(data) => {
+                warnings
+                    .push("Your progress was damaged and has been restored from a backup.".into());
+                Ok(Some(data))
+            }
+            None => {
+                warnings.push("Your progress was damaged and has been reset, sorry!".into());
+                Ok(None)
+            }
+        }
+    }
+
+    /// Writes the user's progress to `data.json`. The data is written to a
+    /// temporary file which then replaces `data.json`, so a crash part way
+    /// through never leaves a half-written file behind, and the previous
+    /// `data.json` is kept as `data.json.bak`.
+    fn write(&self) -> Result<(), String> {
+        let content = match serde_json::to_string(&self.serialize()) {
             Ok(c) => c,
             Err(e) => return Err(e.to_string()),
         };
-        if let Err(e) = fs::write(&self.file, content) {
-            return Err(e.to_string());
+        let tmp_file = self.file.to_string() + ".tmp";
+        let mut tmp = fs::File::create(&tmp_file).map_err(|e| e.to_string())?;
+        tmp.write_all(content.as_bytes())
+            .and_then(|_| tmp.sync_all())
+            .map_err(|e| e.to_string())?;
+        if path::Path::new(&self.file).exists() {
+            fs::copy(&self.file, self.file.to_string() + ".bak").map_err(|e| e.to_string())?;
+        }
+        fs::rename(&tmp_file, &self.file).map_err(|e| e.to_string())
+    }
+
+    fn serialize(&self) -> SerializedUserData {
+        SerializedUserData {
+            version: SerializedUserData::VERSION,
+            completed_core_levels: self.completed_core_levels.clone(),
+            completed_levels: self.completed_levels.clone(),
+            completed_pack_levels: self.completed_pack_levels.clone(),
+            stats: self.stats.clone(),
+        }
+    }
+
+    /// Writes the profile's progress to `file` so it can be imported on
+    /// another computer. The export uses the same format as `data.json`.
+    pub fn export_progress(&self, file: &path::Path) -> Result<(), String> {
+        let content = serde_json::to_string_pretty(&self.serialize()).map_err(|e| e.to_string())?;
+        fs::write(file, content).map_err(|e| format!("{}: {e}", file.display()))
+    }
+
+    /// Merges the progress exported to `file` into this profile, returning
+    /// how many newly completed levels were added.
+    pub fn import_progress(&mut self, file: &path::Path) -> Result<usize, String> {
+        let content = fs::read_to_string(file).map_err(|e| format!("{}: {e}", file.display()))?;
+        let data = match SerializedUserData::parse(&content) {
+            Ok(data) => data,
+            Err(DataError::Newer(version)) => {
+                return Err(format!(
+                    "{} was exported by a newer version of l1t (format version {version})",
+                    file.display()
+                ))
+            }
+            Err(DataError::Corrupt) => {
+                return Err(format!("{} is not an l1t progress export", file.display()))
+            }
         };
-        Ok(())
+        let added = self.merge(data);
+        self.write()?;
+        Ok(added)
+    }
+
+    /// Merges `data` into this profile's progress without losing anything
+    /// from either side: completions are combined keeping the earliest
+    /// completion time, and the best move counts are kept. The totals in
+    /// the stats take the larger of the two, so importing the same export
+    /// twice doesn't count anything twice.
+    fn merge(&mut self, data: SerializedUserData) -> usize {
+        let mut added = 0;
+        for level in data.completed_core_levels {
+            if !self.completed_core_levels.contains(&level) {
+                self.completed_core_levels.push(level);
+                added += 1;
+            }
+        }
+        self.completed_core_levels.sort();
+        for level in data.completed_levels {
+            match self
+                .completed_levels
+                .iter_mut()
+                .find(|l| l.matches(&level.url, level.hash.as_deref()))
+            {
+                Some(existing) => {
+                    existing.completed_at = existing.completed_at.min(level.completed_at)
+                }
+                None => {
+                    self.completed_levels.push(level);
+                    added += 1;
+                }
+            }
+        }
+        for (pack, levels) in data.completed_pack_levels {
+            let completed = self.completed_pack_levels.entry(pack).or_default();
+            for level in levels {
+                if !completed.contains(&level) {
+                    completed.push(level);
+                    added += 1;
+                }
+            }
+            completed.sort();
+        }
+        self.stats.levels_won = self.stats.levels_won.max(data.stats.levels_won);
+        self.stats.levels_lost = self.stats.levels_lost.max(data.stats.levels_lost);
+        self.stats.total_moves = self.stats.total_moves.max(data.stats.total_moves);
+        for (key, moves) in data.stats.best_moves {
+            let best = self.stats.best_moves.entry(key).or_insert(moves);
+            *best = moves.min(*best);
+        }
+        added
+    }
+
+    #[allow(clippy::manual_contains)]
+    fn complete_core(&mut self, level: usize) -> Result<(), String> {
+        if self.completed_core_levels.iter().any(|i| *i == level) {
+            return Ok(());
+        }
+        self.completed_core_levels.push(level);
+        self.write()
     }
 
     fn complete_repo(&mut self, level_info: LevelInfo) -> Result<(), String> {
-        if let LevelSource::Url(url) = level_info.source {
-            if self.completed_levels.iter().any(|l| {
-                l.url == url || (l.name == level_info.name && l.author == level_info.author)
-            }) {
+        if let Some(url) = level_info.source.url() {
+            let hash = level_info.meta.hash;
+            if self
+                .completed_levels
+                .iter()
+                .any(|l| l.matches(&url, hash.as_deref()))
+            {
                 return Ok(());
             }
             let completed_at = SystemTime::now()
                 .duration_since(UNIX_EPOCH)
                 .unwrap()
                 .as_secs();
+            let commit = match level_info.source {
+                LevelSource::Git { commit, .. } => Some(commit),
+                _ => None,
+            };
             self.completed_levels.push(CompletedRepoLevel {
                 url,
                 completed_at,
                 name: level_info.name,
                 author: level_info.author,
                 description: level_info.description,
+                hash,
+                commit,
             });
-            let content = match serde_json::to_string(&SerializedUserData {
-                file: self.file.clone(),
-                completed_core_levels: self.completed_core_levels.clone(),
-                completed_levels: self.completed_levels.clone(),
-            }) {
-                Ok(c) => c,
-                Err(e) => return Err(e.to_string()),
-            };
-            if let Err(e) = fs::write(&self.file, content) {
-                return Err(e.to_string());
-            };
+            self.write()?;
         }
         Ok(())
     }
 
+    fn complete_pack(&mut self, path: &path::Path, level: usize) -> Result<(), String> {
+        let completed = self
+            .completed_pack_levels
+            .entry(path.to_string_lossy().to_string())
+            .or_default();
+        if completed.contains(&level) {
+            return Ok(());
+        }
+        completed.push(level);
+        completed.sort();
+        self.write()
+    }
+
+    /// Records that the player completed the level. Levels from files are
+    /// recorded like repository levels, by their `file://` URL.
     pub fn complete(&mut self, level_info: LevelInfo) -> Result<(), String> {
-        match level_info.source {
-            LevelSource::Core(level) => self.complete_core(level),
-            LevelSource::Url(_) => self.complete_repo(level_info),
-            _ => Err("".to_string()),
+        match &level_info.source {
+            LevelSource::Core(level) => self.complete_core(*level),
+            LevelSource::Url(_) | LevelSource::File(_) | LevelSource::Git { .. } => {
+                self.complete_repo(level_info)
+            }
+            LevelSource::Pack { path, level } => self.complete_pack(path, *level),
+        }
+    }
+
+    /// The levels the player has completed in `pack`.
+    pub fn completed_in(&self, pack: &Pack) -> Vec<usize> {
+        self.completed_pack_levels
+            .get(&pack.key())
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Saves the state of a level the player quit so it can be resumed
+    /// later. Levels the player hasn't played any moves in yet, including
+    /// toggling nodes, aren't saved.
+    pub fn save_level(&self, level: Level) -> Result<(), String> {
+        if level.history.is_empty() {
+            return Ok(());
+        }
+        SavedLevel::new(level).write(&self.saves_dir)
+    }
+
+    /// The level the player most recently quit, if any.
+    pub fn saved_level(&self) -> Option<SavedLevel> {
+        SavedLevel::latest(&self.saves_dir)
+    }
+
+    pub fn discard_save(&self, source: &LevelSource) {
+        SavedLevel::remove(&self.saves_dir, source);
+    }
+
+    /// Updates the player's stats after they won or lost `level`.
+    pub fn record_result(&mut self, level: &Level, won: bool) -> Result<(), String> {
+        self.stats.total_moves += level.moves as u64;
+        if won {
+            self.stats.levels_won += 1;
+            let best = self
+                .stats
+                .best_moves
+                .entry(level.info.source.key())
+                .or_insert(level.moves);
+            *best = level.moves.min(*best);
+        } else {
+            self.stats.levels_lost += 1;
+        }
+        self.write()
+    }
+
+    /// Replaces the player's settings, saving them and putting them into
+    /// effect straight away.
+    pub fn save_settings(&mut self, settings: Settings) -> Result<(), String> {
+        let theme = Theme::load(&settings.theme, &self.paths.config)?;
+        settings.write(&UserData::profile_dir(&self.paths.data, &self.profile))?;
+        settings.clone().install();
+        theme.install();
+        self.settings = settings;
+        Ok(())
+    }
+
+    /// Writes the player's repositories back to `repositories.toml`.
+    pub fn save_repositories(&self) -> Result<(), String> {
+        Repository::write_all(&self.paths.config, &self.repositories)
+    }
+
+    /// The configured repository that `url` belongs to, if any.
+    pub fn repository_for(&self, url: &str) -> Option<&Repository> {
+        self.repositories
+            .iter()
+            .filter_map(|r| Some((r, r.root_url().ok()?)))
+            .filter(|(_, root)| url.starts_with(root.as_str()))
+            .max_by_key(|(_, root)| root.as_str().len())
+            .map(|(r, _)| r)
+    }
+
+    /// The names of every theme the player can choose from.
+    pub fn themes(&self) -> Vec<String> {
+        Theme::names(&self.paths.config)
+    }
+
+    pub const DEFAULT_PROFILE: &'static str = "default";
+
+    fn profile_dir(data_dir: &str, profile: &str) -> String {
+        format!("{data_dir}/profiles/{profile}")
+    }
+
+    /// Profile names are used as directory names, so they are limited to
+    /// letters, numbers, spaces, `-` and `_`.
+    pub fn validate_profile_name(name: &str) -> Result<(), String> {
+        if name.trim().is_empty() || name.len() > 32 {
+            return Err("Profile names must be between 1 and 32 characters long".to_string());
+        }
+        if !name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == ' ' || c == '-' || c == '_')
+        {
+            return Err(
+                "Profile names can only contain letters, numbers, spaces, `-` and `_`".to_string(),
+            );
+        }
+        Ok(())
+    }
+
+    /// Moves the progress from before profiles existed, which was kept
+    /// directly in the data directory, into the default profile.
+    fn migrate_to_profiles(data_dir: &str) -> Result<(), String> {
+        if path::Path::new(&(data_dir.to_string() + "/profiles")).exists()
+            || !path::Path::new(&(data_dir.to_string() + "/data.json")).exists()
+        {
+            return Ok(());
+        }
+        let profile_dir = UserData::profile_dir(data_dir, UserData::DEFAULT_PROFILE);
+        if let Err(e) = fs::create_dir_all(&profile_dir) {
+            return Err(e.to_string());
+        }
+        for name in ["data.json", "data.json.bak", "saves"] {
+            let from = format!("{data_dir}/{name}");
+            if path::Path::new(&from).exists() {
+                if let Err(e) = fs::rename(&from, format!("{profile_dir}/{name}")) {
+                    return Err(e.to_string());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// The names of every profile, sorted.
+    pub fn profiles(&self) -> Vec<String> {
+        let mut profiles: Vec<String> = fs::read_dir(self.paths.data.to_string() + "/profiles")
+            .map(|dir| {
+                dir.filter_map(|d| d.ok())
+                    .filter(|d| d.path().is_dir())
+                    .map(|d| d.file_name().to_string_lossy().to_string())
+                    .collect()
+            })
+            .unwrap_or_default();
+        profiles.sort_by(|a, b| human_sort::compare(a, b));
+        profiles
+    }
+
+    /// Switches to `profile`, creating it if it doesn't exist yet.
+    pub fn switch_profile(&mut self, profile: &str) -> Result<(), String> {
+        *self = UserData::read(self.paths.clone(), Some(profile.to_string()))?;
+        Ok(())
+    }
+
+    /// Creates the profile `to` with a copy of the progress, stats,
+    /// settings and saves of the profile `from`.
+    pub fn copy_profile(&self, from: &str, to: &str) -> Result<(), String> {
+        UserData::validate_profile_name(to)?;
+        let to_dir = UserData::profile_dir(&self.paths.data, to);
+        if path::Path::new(&to_dir).exists() {
+            return Err(format!("A profile named `{to}` already exists"));
+        }
+        copy_dir(
+            path::Path::new(&UserData::profile_dir(&self.paths.data, from)),
+            path::Path::new(&to_dir),
+        )
+        .map_err(|e| e.to_string())
+    }
+
+    /// Deletes all of the progress, stats and saves of `profile`.
+    pub fn reset_profile(&mut self, profile: &str) -> Result<(), String> {
+        let profile_dir = UserData::profile_dir(&self.paths.data, profile);
+        for name in ["data.json", "data.json.bak"] {
+            fs::remove_file(format!("{profile_dir}/{name}")).ok();
+        }
+        fs::remove_dir_all(format!("{profile_dir}/saves")).ok();
+        if profile == self.profile {
+            let current = self.profile.clone();
+            self.switch_profile(&current)?;
+        }
+        Ok(())
+    }
+}
+
+fn copy_dir(from: &path::Path, to: &path::Path) -> std::io::Result<()> {
+    fs::create_dir_all(to)?;
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let to = to.join(entry.file_name());
+        if entry.path().is_dir() {
+            copy_dir(&entry.path(), &to)?;
+        } else {
+            fs::copy(entry.path(), to)?;
         }
     }
+    Ok(())
 }
@@ -0,0 +1,35 @@
+//! Fixtures shared by the integration tests. Each test file only uses
+//! some of them.
+#![allow(dead_code)]
+
+use l1t::cache::Cache;
+use std::{fs, path::PathBuf};
+
+/// The grid of a level from the example repository.
+pub const LEVEL: &str = include_str!("../../examples/repo/levels/test_level1.l1t");
+
+/// A level won by stepping down and pulling the block out of the laser's
+/// way, with the moves `Dp`.
+pub const PULL_GRID: &str = "IIIIIIII
+I  X   I
+I      I
+I4 B  SI
+IIIIIIII";
+
+/// The whole `.l1t` file of the level with [`PULL_GRID`].
+pub fn pull_level() -> String {
+    format!("Pull It Out!\nme\nA block in the way\n{PULL_GRID}\n")
+}
+
+/// An empty directory for the test named `name`.
+pub fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("l1t-test-{}-{name}", std::process::id()));
+    fs::remove_dir_all(&dir).ok();
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// An empty cache for the test named `name`.
+pub fn cache(name: &str) -> Cache {
+    Cache::new(&temp_dir(name).to_string_lossy())
+}
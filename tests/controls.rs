@@ -0,0 +1,104 @@
+use crossterm::event::{KeyCode, KeyModifiers};
+use l1t::controls::{Control, KeyBinding, Keymap};
+
+fn key(code: KeyCode, modifiers: KeyModifiers) -> KeyBinding {
+    KeyBinding::new(code, modifiers)
+}
+
+#[test]
+fn keys_are_parsed_with_their_modifiers() {
+    for (text, binding) in [
+        ("q", key(KeyCode::Char('q'), KeyModifiers::NONE)),
+        ("Space", key(KeyCode::Char(' '), KeyModifiers::NONE)),
+        (" enter ", key(KeyCode::Enter, KeyModifiers::NONE)),
+        ("Return", key(KeyCode::Enter, KeyModifiers::NONE)),
+        ("Esc", key(KeyCode::Esc, KeyModifiers::NONE)),
+        ("F12", key(KeyCode::F(12), KeyModifiers::NONE)),
+        ("Shift+Up", key(KeyCode::Up, KeyModifiers::SHIFT)),
+        ("Ctrl+c", key(KeyCode::Char('c'), KeyModifiers::CONTROL)),
+        (
+            "control+alt+x",
+            key(
+                KeyCode::Char('x'),
+                KeyModifiers::CONTROL | KeyModifiers::ALT,
+            ),
+        ),
+        ("+", key(KeyCode::Char('+'), KeyModifiers::NONE)),
+        ("Ctrl++", key(KeyCode::Char('+'), KeyModifiers::CONTROL)),
+    ] {
+        assert_eq!(KeyBinding::parse(text), Ok(binding), "{text}");
+    }
+    for text in ["", "F13", "F0", "Hyper+a", "Ctrl+", "PgUp", "ab"] {
+        assert!(KeyBinding::parse(text).is_err(), "{text}");
+    }
+}
+
+#[test]
+fn shift_is_folded_into_characters() {
+    let upper_h = key(KeyCode::Char('H'), KeyModifiers::NONE);
+    assert_eq!(KeyBinding::parse("Shift+h"), Ok(upper_h));
+    assert_eq!(KeyBinding::parse("H"), Ok(upper_h));
+    assert_eq!(key(KeyCode::Char('h'), KeyModifiers::SHIFT), upper_h);
+    assert_ne!(KeyBinding::parse("h"), Ok(upper_h));
+    // Shift stays on keys that aren't characters.
+    assert_ne!(KeyBinding::parse("Shift+Up"), KeyBinding::parse("Up"));
+    assert_eq!(upper_h.to_string(), "H");
+    assert_eq!(
+        KeyBinding::parse("Ctrl+Shift+Left").unwrap().to_string(),
+        "Ctrl+Shift+Left"
+    );
+}
+
+#[test]
+fn controls_left_out_keep_their_default_keys() {
+    let keymap = Keymap::parse("quit = [\"Esc\"]").unwrap();
+    assert_eq!(keymap.describe(Control::Quit), "Esc");
+    assert_eq!(keymap.describe(Control::Up), "Up, w, k");
+    assert_eq!(
+        keymap.control(&KeyBinding::parse("q").unwrap()),
+        Control::None
+    );
+    assert_eq!(
+        Keymap::parse("").unwrap().describe(Control::Select),
+        "Enter"
+    );
+}
+
+#[test]
+fn default_keys_the_file_uses_are_left_out() {
+    // A keys file from before `pull`, `delete` and `search` had keys.
+    let keymap =
+        Keymap::parse("action = [\"p\", \"Space\"]\nquit = [\"q\", \"x\", \"/\"]").unwrap();
+    let p = KeyBinding::parse("p").unwrap();
+    assert_eq!(keymap.control(&p), Control::Action);
+    assert!(keymap.keys(Control::Pull).is_empty());
+    assert!(keymap.keys(Control::Delete).is_empty());
+    assert!(keymap.keys(Control::Search).is_empty());
+    assert_eq!(keymap.describe(Control::Edit), "e");
+}
+
+#[test]
+fn bad_keys_files_are_errors() {
+    for (content, error) in [
+        ("jump = [\"Space\"]", "unknown control `jump`"),
+        (
+            "up = [\"Up\"]\ndown = [\"Up\"]",
+            "`Up` is bound to both `up` and `down`",
+        ),
+        (
+            "up = [\"H\"]\nhelp = [\"Shift+h\"]",
+            "`H` is bound to both `up` and `help`",
+        ),
+        ("select = []", "`select` must be bound to at least one key"),
+        ("quit = []", "`quit` must be bound to at least one key"),
+        ("up = [\"Nope\"]", "unknown key `Nope`"),
+        ("up = \"w\"", ""),
+    ] {
+        let e = Keymap::parse(content).unwrap_err();
+        assert!(e.contains(error), "{content}: {e}");
+    }
+    // Listing a key twice for the same control is fine.
+    assert!(Keymap::parse("up = [\"w\", \"w\"]").is_ok());
+    // Controls other than select and quit can be left unbound.
+    assert!(Keymap::parse("help = []").is_ok());
+}
@@ -0,0 +1,344 @@
+use l1t::{
+    direction::Direction,
+    level::{Interaction, Level, LevelSource},
+    moves::Move,
+    node::NodeType,
+};
+
+mod common;
+use common::PULL_GRID;
+
+fn level(options: &str, grid: &str) -> Level {
+    let options = if options.is_empty() {
+        String::new()
+    } else {
+        format!("{options}\n")
+    };
+    let content = format!("Test\nl1t\nA test level\n{options}{grid}");
+    Level::parse(&content, LevelSource::Core(0)).expect("level should parse")
+}
+
+fn player(level: &Level) -> (u16, u16) {
+    let player = &level.nodes[level.player_index.unwrap()];
+    (player.row, player.col)
+}
+
+fn blocks(level: &Level) -> Vec<(u16, u16)> {
+    level
+        .nodes
+        .iter()
+        .filter(|n| matches!(n.node_type, NodeType::Block(_)))
+        .map(|n| (n.row, n.col))
+        .collect()
+}
+
+#[test]
+fn pushes_one_block_by_default() {
+    let mut level = level(
+        "",
+        "IIIIIIII
+I XB   I
+IIIIIIII",
+    );
+    level.move_player(Direction::RIGHT);
+    assert_eq!(player(&level), (1, 3));
+    assert_eq!(blocks(&level), vec![(1, 4)]);
+}
+
+#[test]
+fn chain_longer_than_push_strength_does_not_move() {
+    let mut level = level(
+        "",
+        "IIIIIIII
+I XBB  I
+IIIIIIII",
+    );
+    level.move_player(Direction::RIGHT);
+    assert_eq!(player(&level), (1, 2));
+    assert_eq!(blocks(&level), vec![(1, 3), (1, 4)]);
+}
+
+#[test]
+fn pushes_chain_up_to_push_strength() {
+    let mut level = level(
+        "push = 3",
+        "IIIIIIII
+IXBB?  I
+IIIIIIII",
+    );
+    level.move_player(Direction::RIGHT);
+    assert_eq!(player(&level), (1, 2));
+    assert_eq!(blocks(&level), vec![(1, 3), (1, 4)]);
+    let mirror = level
+        .nodes
+        .iter()
+        .find(|n| matches!(n.node_type, NodeType::Mirror(_)))
+        .unwrap();
+    assert_eq!((mirror.row, mirror.col), (1, 5));
+}
+
+#[test]
+fn chain_against_wall_does_not_move() {
+    let mut level = level(
+        "push = 5",
+        "IIIIIII
+I  XBBI
+IIIIIII",
+    );
+    level.move_player(Direction::RIGHT);
+    assert_eq!(player(&level), (1, 3));
+    assert_eq!(blocks(&level), vec![(1, 4), (1, 5)]);
+}
+
+#[test]
+fn chain_pushed_until_it_reaches_wall() {
+    let mut level = level(
+        "push = 2",
+        "IIIIIII
+IXBB  I
+IIIIIII",
+    );
+    level.move_player(Direction::RIGHT);
+    level.move_player(Direction::RIGHT);
+    level.move_player(Direction::RIGHT);
+    assert_eq!(player(&level), (1, 3));
+    assert_eq!(blocks(&level), vec![(1, 4), (1, 5)]);
+}
+
+#[test]
+fn chain_with_fixed_node_does_not_move() {
+    let mut level = level(
+        "push = 3",
+        "IIIIIIII
+IXB/B  I
+IIIIIIII",
+    );
+    level.move_player(Direction::RIGHT);
+    assert_eq!(player(&level), (1, 1));
+    assert_eq!(blocks(&level), vec![(1, 2), (1, 4)]);
+}
+
+#[test]
+fn vertical_chain_against_wall_does_not_move() {
+    let mut level = level(
+        "push = 2",
+        "IIII
+I XI
+I BI
+I BI
+IIII",
+    );
+    level.move_player(Direction::DOWN);
+    assert_eq!(player(&level), (1, 2));
+    assert_eq!(blocks(&level), vec![(2, 2), (3, 2)]);
+}
+
+#[test]
+fn zero_push_strength_cannot_push() {
+    let mut level = level(
+        "push = 0",
+        "IIIIII
+IXB  I
+IIIIII",
+    );
+    level.move_player(Direction::RIGHT);
+    assert_eq!(player(&level), (1, 1));
+    assert_eq!(blocks(&level), vec![(1, 2)]);
+}
+
+/// Loads `grid` with the player facing left, towards the node on their
+/// left if there is one.
+fn facing_left(grid: &str) -> Level {
+    let mut level = level("", grid);
+    level.move_player(Direction::LEFT);
+    level
+}
+
+fn statues(level: &Level) -> Vec<(u16, u16)> {
+    level
+        .nodes
+        .iter()
+        .filter(|n| matches!(n.node_type, NodeType::Statue(_)))
+        .map(|n| (n.row, n.col))
+        .collect()
+}
+
+#[test]
+fn pulls_the_block_the_player_is_facing() {
+    let mut level = facing_left(
+        "IIIIIII
+IBX   I
+IIIIIII",
+    );
+    level.apply(Move::Pull);
+    assert_eq!(player(&level), (1, 3));
+    assert_eq!(blocks(&level), vec![(1, 2)]);
+    assert_eq!(level.moves, 1);
+}
+
+#[test]
+fn pull_against_wall_does_not_move() {
+    let mut level = facing_left(
+        "IIII
+IBXI
+IIII",
+    );
+    level.apply(Move::Pull);
+    assert_eq!(player(&level), (1, 2));
+    assert_eq!(blocks(&level), vec![(1, 1)]);
+    assert_eq!(level.moves, 0);
+}
+
+#[test]
+fn pull_into_another_node_does_not_move() {
+    let mut level = facing_left(
+        "IIIIIII
+IBXB  I
+IIIIIII",
+    );
+    level.apply(Move::Pull);
+    assert_eq!(player(&level), (1, 2));
+    assert_eq!(blocks(&level), vec![(1, 1), (1, 3)]);
+}
+
+#[test]
+fn pull_with_nothing_to_pull_does_not_move() {
+    let mut level = facing_left(
+        "IIIIIII
+IX    I
+IIIIIII",
+    );
+    level.apply(Move::Pull);
+    assert_eq!(player(&level), (1, 1));
+    assert_eq!(level.moves, 0);
+}
+
+#[test]
+fn fixed_nodes_cannot_be_pulled() {
+    let mut level = facing_left(
+        "IIIIIII
+ISX   I
+IIIIIII",
+    );
+    level.apply(Move::Pull);
+    assert_eq!(player(&level), (1, 2));
+    assert_eq!(statues(&level), vec![(1, 1)]);
+}
+
+#[test]
+fn invalid_push_strength_is_an_error() {
+    let content = "Test\nl1t\nA test level\npush = lots\nIIII\nIX I\nIIII";
+    assert!(Level::parse(content, LevelSource::Core(0)).is_err());
+}
+
+#[test]
+fn options_default_when_there_are_none() {
+    let level = level("", "IIII\nIX I\nIIII");
+    assert_eq!(level.options.interaction, Interaction::All);
+    assert_eq!(level.options.push_strength, 1);
+    assert_eq!(level.rows, 3);
+}
+
+#[test]
+fn options_are_read_until_the_grid_starts() {
+    let level = level("interaction = single\n  push=3  ", "IIII\nIX I\nIIII");
+    assert_eq!(level.options.interaction, Interaction::Single);
+    assert_eq!(level.options.push_strength, 3);
+    assert_eq!(level.rows, 3);
+    assert_eq!(level.cols, 4);
+}
+
+#[test]
+fn unknown_options_and_bad_values_are_errors() {
+    for option in ["speed = 3", "interaction = some", "push = -1", "push ="] {
+        let content = format!("Test\nl1t\nA test level\n{option}\nIIII\nIX I\nIIII");
+        assert!(
+            Level::parse(&content, LevelSource::Core(0)).is_err(),
+            "{option}"
+        );
+    }
+}
+
+#[test]
+fn options_after_the_grid_starts_are_not_options() {
+    let content = "Test\nl1t\nA test level\nIIII\npush = 2\nIX I\nIIII";
+    assert!(Level::parse(content, LevelSource::Core(0)).is_err());
+}
+
+#[test]
+fn replays_are_won_by_their_last_move() {
+    let mut level = level("", PULL_GRID);
+    let moves = Move::parse("Dp").unwrap();
+    assert!(level.replay(&moves));
+    assert_eq!(level.history, moves);
+}
+
+#[test]
+fn replays_that_stop_short_or_carry_on_are_not_wins() {
+    for moves in ["", "D", "DpU", "DRp"] {
+        let mut level = level("", PULL_GRID);
+        assert!(!level.replay(&Move::parse(moves).unwrap()), "{moves}");
+    }
+}
+
+#[test]
+fn moves_are_written_one_letter_each() {
+    let moves = Move::parse("UDLR udlr\nxp").unwrap();
+    assert_eq!(moves[0], Move::Step(Direction::UP));
+    assert_eq!(moves[5], Move::Toggle(Direction::DOWN));
+    assert_eq!(Move::notation(&moves), "UDLRudlrxp");
+    assert!(Move::parse("UQ").is_err());
+}
+
+/// The state of every node in `level`, to see what a move changed.
+fn states(level: &Level) -> Vec<String> {
+    level
+        .nodes
+        .iter()
+        .map(|n| format!("{:?}", n.node_type))
+        .collect()
+}
+
+/// The indexes of the nodes whose state differs between `a` and `b`.
+fn changed(a: &[String], b: &[String]) -> Vec<usize> {
+    (0..a.len()).filter(|i| a[*i] != b[*i]).collect()
+}
+
+const CLICK_GRID: &str = "IIIIIII
+I /  /I
+I/X/  I
+I  /  I
+IIIIIII";
+
+#[test]
+fn clicking_a_neighbour_toggles_only_it() {
+    let mut level = level("", CLICK_GRID);
+    let before = states(&level);
+    level.click_at(1, 2);
+    let after = states(&level);
+    let toggled = changed(&before, &after);
+    assert_eq!(toggled.len(), 1);
+    let node = &level.nodes[toggled[0]];
+    assert_eq!((node.row, node.col), (1, 2));
+    assert_eq!(level.history, vec![Move::Toggle(Direction::UP)]);
+    assert_eq!(player(&level), (2, 2));
+
+    level.click_at(2, 3);
+    assert_eq!(
+        level.history,
+        vec![Move::Toggle(Direction::UP), Move::Toggle(Direction::RIGHT)]
+    );
+}
+
+#[test]
+fn clicks_away_from_the_player_are_ignored() {
+    let mut level = level("", CLICK_GRID);
+    let before = states(&level);
+    // A diagonal node, a node two spaces away, an empty space next to the
+    // player, the player, a wall and spaces outside the grid.
+    for (row, col) in [(3, 3), (1, 5), (3, 2), (2, 2), (0, 2), (2, 9), (40, 1)] {
+        level.click_at(row, col);
+    }
+    assert!(changed(&before, &states(&level)).is_empty());
+    assert!(level.history.is_empty());
+}
@@ -0,0 +1,68 @@
+use l1t::{controls::Control, menu::Menu};
+
+/// Items drawn one per row from row 5, between columns 10 and 30.
+fn item_at(col: u16, row: u16) -> Option<usize> {
+    (0..3).find(|i| row == 5 + *i as u16 && col > 10 && col < 30)
+}
+
+#[test]
+fn clicking_an_item_selects_it() {
+    let mut selection = 0;
+    let control = Menu::select_clicked(Control::Click(12, 7), &mut selection, item_at);
+    assert_eq!(control, Control::Select);
+    assert_eq!(selection, 2);
+
+    for (col, row) in [(12, 4), (12, 8), (10, 6), (30, 6)] {
+        let control = Menu::select_clicked(Control::Click(col, row), &mut selection, item_at);
+        assert_eq!(control, Control::None, "({col}, {row})");
+        assert_eq!(selection, 2);
+    }
+    let control = Menu::select_clicked(Control::Down, &mut selection, item_at);
+    assert_eq!(control, Control::Down);
+    assert_eq!(selection, 2);
+}
+
+#[test]
+fn long_messages_are_wrapped_to_fit() {
+    assert_eq!(
+        Menu::wrap("Couldn't check main", 40),
+        vec!["Couldn't check main"]
+    );
+    assert_eq!(
+        Menu::wrap("Couldn't check main: the repository is down", 20),
+        vec!["Couldn't check main:", "the repository is", "down"]
+    );
+    // Words longer than a line, like URLs, are split.
+    assert_eq!(
+        Menu::wrap("at https://example.com/l1t", 10),
+        vec!["at", "https://ex", "ample.com/", "l1t"]
+    );
+    assert_eq!(Menu::wrap("", 10), vec![""]);
+    for line in Menu::wrap(&"word ".repeat(50), 12) {
+        assert!(line.chars().count() <= 12, "{line}");
+    }
+}
+
+#[test]
+fn listings_taller_than_the_terminal_scroll_with_the_selection() {
+    // 500 levels with room for 16 rows.
+    let (levels, rows) = (500, 16);
+    let mut start = 0;
+    for selection in 0..levels {
+        start = Menu::scroll_to(start, selection, rows);
+        assert!((start..start + rows).contains(&selection), "{selection}");
+        assert!(start + rows <= levels);
+    }
+    assert_eq!(start, levels - rows);
+    // Going back up only scrolls once the selection leaves the top.
+    assert_eq!(Menu::scroll_to(start, levels - rows, rows), levels - rows);
+    assert_eq!(
+        Menu::scroll_to(start, levels - rows - 1, rows),
+        levels - rows - 1
+    );
+    // Wrapping around from the last level jumps back to the top.
+    assert_eq!(Menu::scroll_to(start, 0, rows), 0);
+    assert_eq!(Menu::scroll_to(0, levels - 1, rows), levels - rows);
+    // Listings that fit never scroll.
+    assert_eq!(Menu::scroll_to(0, 9, rows), 0);
+}
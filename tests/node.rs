@@ -0,0 +1,55 @@
+use l1t::{
+    node::{Node, NodeType},
+    theme::{ColorSupport, Theme},
+};
+
+/// Every kind of node in every state it can be drawn in.
+fn every_state() -> Vec<Node> {
+    let mut nodes = vec![];
+    for ch in [
+        'X', 'B', 'I', 'T', '/', '\\', '?', '|', '1', '5', 's', 'b', 'S', 'R', 'Z',
+    ] {
+        for on in [false, true] {
+            let mut node = Node::new(ch, 0, 0);
+            match &mut node.node_type {
+                NodeType::Switch(s) => s.on = on,
+                NodeType::Button(b) => b.pressed = on,
+                NodeType::Statue(s) => s.lit = on,
+                NodeType::Zapper(z) => z.lit = on,
+                NodeType::Player(p) => p.dead = on,
+                _ if on => continue,
+                _ => {}
+            }
+            nodes.push(node);
+        }
+    }
+    nodes
+}
+
+#[test]
+fn every_node_state_is_drawn_differently_without_colors() {
+    let theme = Theme::dark().adapted(ColorSupport::None);
+    let nodes = every_state();
+    for (i, a) in nodes.iter().enumerate() {
+        for b in &nodes[i + 1..] {
+            assert_ne!(
+                a.glyph(&theme),
+                b.glyph(&theme),
+                "{:?} and {:?}",
+                a.node_type,
+                b.node_type
+            );
+        }
+    }
+}
+
+#[test]
+fn hidden_toggle_blocks_are_not_drawn() {
+    let theme = Theme::dark();
+    let mut node = Node::new('T', 0, 0);
+    assert!(node.glyph(&theme).is_some());
+    if let NodeType::ToggleBlock(t) = &mut node.node_type {
+        t.visible = false;
+    }
+    assert_eq!(node.glyph(&theme), None);
+}
@@ -0,0 +1,92 @@
+use l1t::{level::LevelSource, pack::Pack};
+use std::{fs, io::Write};
+use zip::{write::SimpleFileOptions, ZipWriter};
+
+mod common;
+use common::{temp_dir, LEVEL};
+
+fn level(name: &str) -> String {
+    format!("{name}\nme\nA level\n{LEVEL}")
+}
+
+fn manifest(levels: &[&str]) -> String {
+    let levels: Vec<String> = levels.iter().map(|l| format!("\"{l}\"")).collect();
+    format!(
+        r#"{{"name": "Test Pack", "author": "me", "levels": [{}]}}"#,
+        levels.join(", ")
+    )
+}
+
+#[test]
+fn directory_packs_keep_the_manifest_order() {
+    let dir = temp_dir("directory");
+    fs::create_dir_all(dir.join("levels")).unwrap();
+    fs::write(
+        dir.join("pack.json"),
+        manifest(&["levels/b.l1t", "./a.l1t"]),
+    )
+    .unwrap();
+    fs::write(dir.join("levels/b.l1t"), level("First")).unwrap();
+    fs::write(dir.join("a.l1t"), level("Second")).unwrap();
+    assert!(Pack::is_pack(&dir));
+    let pack = Pack::open(&dir).unwrap();
+    assert_eq!(pack.name, "Test Pack");
+    let names: Vec<&str> = pack.levels.iter().map(|l| l.name.as_str()).collect();
+    assert_eq!(names, vec!["First", "Second"]);
+    let level = pack.level(1).unwrap();
+    assert_eq!(level.info.name, "Second");
+    assert!(matches!(
+        level.info.source,
+        LevelSource::Pack { level: 1, .. }
+    ));
+    assert!(pack.level(2).is_err());
+}
+
+#[test]
+fn archives_are_packs() {
+    let dir = temp_dir("archive");
+    let file = dir.join("pack.zip");
+    let mut zip = ZipWriter::new(fs::File::create(&file).unwrap());
+    for (name, content) in [
+        ("pack.json", manifest(&["one.l1t", "two.l1t"])),
+        ("one.l1t", level("One")),
+        ("two.l1t", level("Two")),
+    ] {
+        zip.start_file(name, SimpleFileOptions::default()).unwrap();
+        zip.write_all(content.as_bytes()).unwrap();
+    }
+    zip.finish().unwrap();
+    assert!(Pack::is_pack(&file));
+    let pack = Pack::open(&file).unwrap();
+    let names: Vec<&str> = pack.levels.iter().map(|l| l.name.as_str()).collect();
+    assert_eq!(names, vec!["One", "Two"]);
+    assert_eq!(pack.level(0).unwrap().info.name, "One");
+}
+
+#[test]
+fn levels_cannot_escape_the_pack() {
+    for (name, path) in [("parent", "../a.l1t"), ("absolute", "/tmp/a.l1t")] {
+        let dir = temp_dir(name);
+        fs::write(dir.join("pack.json"), manifest(&[path])).unwrap();
+        let error = Pack::open(&dir).unwrap_err();
+        assert!(error.contains("outside of the pack"), "{error}");
+    }
+}
+
+#[test]
+fn invalid_packs_are_rejected() {
+    let dir = temp_dir("empty");
+    fs::write(dir.join("pack.json"), manifest(&[])).unwrap();
+    assert!(Pack::open(&dir).is_err());
+
+    let dir = temp_dir("missing");
+    fs::write(dir.join("pack.json"), manifest(&["a.l1t"])).unwrap();
+    assert!(Pack::open(&dir).unwrap_err().starts_with("a.l1t"));
+
+    let dir = temp_dir("invalid");
+    fs::write(dir.join("pack.json"), manifest(&["a.l1t"])).unwrap();
+    fs::write(dir.join("a.l1t"), "A\nme\n").unwrap();
+    assert!(Pack::open(&dir).unwrap_err().starts_with("a.l1t"));
+
+    assert!(!Pack::is_pack(&temp_dir("not_a_pack")));
+}
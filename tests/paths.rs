@@ -0,0 +1,107 @@
+use l1t::paths::Paths;
+use std::{
+    env, fs,
+    path::Path,
+    sync::{Mutex, MutexGuard},
+};
+
+mod common;
+use common::temp_dir;
+
+/// The environment is shared by every test, so only one test changes it
+/// at a time.
+static ENV: Mutex<()> = Mutex::new(());
+
+/// Sets up the environment for a test with `home` as the home directory
+/// and every other variable `Paths` reads unset.
+fn home(home: &Path) -> MutexGuard<'static, ()> {
+    let guard = ENV.lock().unwrap_or_else(|e| e.into_inner());
+    env::set_var("HOME", home);
+    for var in [
+        "L1T_HOME",
+        "XDG_CONFIG_HOME",
+        "XDG_DATA_HOME",
+        "XDG_CACHE_HOME",
+    ] {
+        env::remove_var(var);
+    }
+    guard
+}
+
+fn dir(path: &Path) -> String {
+    path.to_string_lossy().to_string()
+}
+
+#[test]
+fn the_data_dir_option_wins_over_l1t_home() {
+    let root = temp_dir("paths_single");
+    let _env = home(&root.join("home"));
+    env::set_var("L1T_HOME", root.join("env"));
+    let paths = Paths::new(Some(dir(&root.join("option"))));
+    assert_eq!(paths.config, dir(&root.join("option")));
+    assert_eq!(paths.data, dir(&root.join("option")));
+    assert_eq!(paths.cache, dir(&root.join("option/cache")));
+
+    let paths = Paths::new(None);
+    assert_eq!(paths.data, dir(&root.join("env")));
+    assert!(!paths.temporary);
+
+    env::set_var("L1T_HOME", "");
+    let paths = Paths::new(None);
+    assert_eq!(paths.data, dir(&root.join("home/.local/share/l1t")));
+}
+
+#[test]
+fn xdg_directories_are_used_when_absolute() {
+    let root = temp_dir("paths_xdg");
+    let _env = home(&root.join("home"));
+    env::set_var("XDG_CONFIG_HOME", root.join("config"));
+    env::set_var("XDG_DATA_HOME", "relative/data");
+    let paths = Paths::new(None);
+    assert_eq!(paths.config, dir(&root.join("config/l1t")));
+    assert_eq!(paths.data, dir(&root.join("home/.local/share/l1t")));
+    assert_eq!(paths.cache, dir(&root.join("home/.cache/l1t")));
+}
+
+#[test]
+fn the_old_l1t_directory_is_moved() {
+    let root = temp_dir("paths_legacy");
+    let old = root.join(".l1t");
+    fs::create_dir_all(old.join("saves")).unwrap();
+    for file in ["keys.toml", "repositories.l1t_conf", "data.json", "profile"] {
+        fs::write(old.join(file), file).unwrap();
+    }
+    fs::write(old.join("saves/core-0.json"), "{}").unwrap();
+    let _env = home(&root);
+    let paths = Paths::new(None);
+
+    let config = Path::new(&paths.config);
+    let data = Path::new(&paths.data);
+    assert_eq!(
+        fs::read_to_string(config.join("keys.toml")).unwrap(),
+        "keys.toml"
+    );
+    assert!(config.join("repositories.l1t_conf").is_file());
+    assert_eq!(
+        fs::read_to_string(data.join("data.json")).unwrap(),
+        "data.json"
+    );
+    assert!(data.join("profile").is_file());
+    assert!(data.join("saves/core-0.json").is_file());
+    assert!(!old.exists());
+}
+
+#[test]
+fn the_old_l1t_directory_is_left_once_xdg_directories_are_in_use() {
+    let root = temp_dir("paths_legacy_kept");
+    let old = root.join(".l1t");
+    fs::create_dir_all(&old).unwrap();
+    fs::write(old.join("data.json"), "old").unwrap();
+    let data = root.join(".local/share/l1t");
+    fs::create_dir_all(&data).unwrap();
+    fs::write(data.join("data.json"), "new").unwrap();
+    let _env = home(&root);
+    Paths::new(None);
+    assert_eq!(fs::read_to_string(old.join("data.json")).unwrap(), "old");
+    assert_eq!(fs::read_to_string(data.join("data.json")).unwrap(), "new");
+}
@@ -0,0 +1,85 @@
+use l1t::{
+    level::Level,
+    publish::build_repository,
+    repository::{LevelSize, Repository},
+};
+use std::fs;
+
+mod common;
+use common::{cache, temp_dir, LEVEL};
+
+#[tokio::test]
+async fn built_repositories_can_be_played() {
+    let source = temp_dir("source");
+    let out = temp_dir("out");
+    fs::write(
+        source.join("2.l1t"),
+        format!("Two\nme\nThe second\n{LEVEL}"),
+    )
+    .unwrap();
+    fs::write(
+        source.join("10.l1t"),
+        format!("Ten\nyou\nThe tenth\npush = 2\n{LEVEL}"),
+    )
+    .unwrap();
+    fs::write(source.join("notes.txt"), "not a level").unwrap();
+    let built = build_repository(&source, &out).unwrap();
+    assert_eq!(built.len(), 2);
+
+    let mut repository = Repository::new("built".to_string(), format!("file://{}", out.display()));
+    let cache = cache("cache");
+    repository.download_listing(&cache).await.unwrap();
+    let names: Vec<&str> = repository.levels.iter().map(|l| l.name.as_str()).collect();
+    assert_eq!(names, vec!["Two", "Ten"]);
+    let ten = &repository.levels[1];
+    assert_eq!(ten.author, "you");
+    assert_eq!(ten.description, "The tenth");
+    assert_eq!(
+        ten.meta.size,
+        Some(LevelSize {
+            width: 38,
+            height: 14
+        })
+    );
+    assert!(ten.meta.hash.is_some());
+    assert!(ten.meta.last_modified.is_some());
+    let level = Level::url(ten.clone(), &repository, &cache).await.unwrap();
+    assert_eq!(level.options.push_strength, 2);
+}
+
+#[tokio::test]
+async fn levels_with_url_characters_in_their_names_can_be_played() {
+    let source = temp_dir("url_source");
+    let out = temp_dir("url_out");
+    fs::write(source.join("a #1?%.l1t"), format!("Odd\nme\nOdd\n{LEVEL}")).unwrap();
+    let built = build_repository(&source, &out).unwrap();
+    assert_eq!(built[0].source, "levels/a%20%231%3F%25.l1t");
+    assert!(out.join("levels/a #1?%.l1t").is_file());
+
+    let mut repository = Repository::new("built".to_string(), format!("file://{}", out.display()));
+    let cache = cache("url_cache");
+    repository.download_listing(&cache).await.unwrap();
+    let level = Level::url(repository.levels[0].clone(), &repository, &cache)
+        .await
+        .unwrap();
+    assert_eq!(level.info.name, "Odd");
+}
+
+#[test]
+fn invalid_levels_stop_the_build() {
+    let source = temp_dir("invalid_source");
+    let out = temp_dir("invalid_out").join("repo");
+    fs::write(source.join("good.l1t"), format!("Good\nme\nFine\n{LEVEL}")).unwrap();
+    fs::write(source.join("bad.l1t"), "Bad\nme\nBroken\nIII\n").unwrap();
+    let error = build_repository(&source, &out).unwrap_err();
+    assert!(error.starts_with("bad.l1t: "), "{error}");
+    assert!(!out.exists());
+}
+
+#[test]
+fn repositories_are_not_built_over_their_levels() {
+    let source = temp_dir("same");
+    fs::write(source.join("a.l1t"), format!("A\nme\nA level\n{LEVEL}")).unwrap();
+    assert!(build_repository(&source, &source).is_err());
+    assert!(build_repository(&temp_dir("empty"), &temp_dir("empty_out")).is_err());
+}
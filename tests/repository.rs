@@ -0,0 +1,752 @@
+use ed25519_dalek::{Signer, SigningKey};
+use l1t::{
+    cache::{Cache, CacheEntry},
+    level::{checksum, Level, LevelSource},
+    repository::{
+        LevelSize, ListingQuery, Repository, RepositoryError, RepositoryKind, SIGNATURE_HEADER,
+    },
+};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+    sync::{Arc, Mutex},
+};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpListener,
+};
+
+mod common;
+use common::{cache, pull_level, temp_dir, LEVEL};
+
+/// A reply from the stand-in server to requests for `path`.
+struct Route {
+    path: &'static str,
+    body: String,
+    headers: Vec<(&'static str, String)>,
+    status: u16,
+    /// How many more requests are answered with a 500 before `status`.
+    failures: usize,
+}
+
+fn route(path: &'static str, body: impl Into<String>) -> Route {
+    Route {
+        path,
+        body: body.into(),
+        headers: vec![],
+        status: 200,
+        failures: 0,
+    }
+}
+
+impl Route {
+    fn header(mut self, name: &'static str, value: impl Into<String>) -> Route {
+        self.headers.push((name, value.into()));
+        self
+    }
+
+    fn status(mut self, status: u16) -> Route {
+        self.status = status;
+        self
+    }
+
+    /// Answers the first `times` requests with a 500.
+    fn failing(mut self, times: usize) -> Route {
+        self.failures = times;
+        self
+    }
+
+    /// Whether `request` already has the route's `ETag`.
+    fn is_current(&self, request: &str) -> bool {
+        self.headers.iter().any(|(name, etag)| {
+            name.eq_ignore_ascii_case("etag")
+                && request
+                    .to_ascii_lowercase()
+                    .contains(&format!("if-none-match: {}", etag.to_ascii_lowercase()))
+        })
+    }
+}
+
+/// Starts a stand-in repository server that replies to each route's path
+/// with its body and to anything else with a 404. Requests sending a
+/// route's `ETag` back get a 304. Returns its URL and the paths that were
+/// requested from it.
+async fn serve(mut routes: Vec<Route>) -> (String, Arc<Mutex<Vec<String>>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let requests = Arc::new(Mutex::new(vec![]));
+    let log = requests.clone();
+    tokio::spawn(async move {
+        loop {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut buffer = vec![0; 4096];
+            let n = socket.read(&mut buffer).await.unwrap();
+            let request = String::from_utf8_lossy(&buffer[..n]).to_string();
+            let path = request.split_whitespace().nth(1).unwrap_or("").to_string();
+            log.lock().unwrap().push(path.clone());
+            let (status, headers, body) = match routes.iter_mut().find(|r| r.path == path) {
+                Some(route) if route.failures > 0 => {
+                    route.failures -= 1;
+                    (500, String::new(), String::new())
+                }
+                Some(route) => {
+                    let headers: String = route
+                        .headers
+                        .iter()
+                        .map(|(name, value)| format!("{name}: {value}\r\n"))
+                        .collect();
+                    if route.is_current(&request) {
+                        (304, headers, String::new())
+                    } else {
+                        (route.status, headers, route.body.to_string())
+                    }
+                }
+                None => (404, String::new(), String::new()),
+            };
+            let response = format!(
+                "HTTP/1.1 {status} Reply\r\nContent-Length: {}\r\n{headers}Connection: close\r\n\r\n{body}",
+                body.len(),
+            );
+            socket.write_all(response.as_bytes()).await.unwrap();
+        }
+    });
+    (url, requests)
+}
+
+/// The URL of a port nothing is listening on.
+async fn closed_url() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    format!("http://{}", listener.local_addr().unwrap())
+}
+
+fn listing(sources: &[&str]) -> String {
+    let levels: Vec<String> = sources
+        .iter()
+        .map(|s| {
+            format!(r#"{{"source": "{s}", "name": "{s}", "author": "me", "description": "test"}}"#)
+        })
+        .collect();
+    format!(r#"{{"levels": [{}]}}"#, levels.join(", "))
+}
+
+fn urls(repository: &Repository) -> Vec<String> {
+    repository
+        .levels
+        .iter()
+        .map(|l| match &l.source {
+            LevelSource::Url(url) => url.to_string(),
+            source => panic!("unexpected source {source:?}"),
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn trailing_slash_does_not_double_up() {
+    let (url, requests) = serve(vec![route("/l1t", listing(&["levels/a.l1t"]))]).await;
+    let mut repository = Repository::new("test".to_string(), url.to_string() + "/");
+    repository
+        .download_listing(&cache("trailing_slash"))
+        .await
+        .unwrap();
+    assert_eq!(*requests.lock().unwrap(), vec!["/l1t"]);
+    assert_eq!(urls(&repository), vec![url + "/levels/a.l1t"]);
+}
+
+#[tokio::test]
+async fn sources_are_relative_to_a_sub_path() {
+    let (url, requests) = serve(vec![route("/sub/l1t", listing(&["a.l1t", "./b/../c.l1t"]))]).await;
+    for root in [url.to_string() + "/sub", url.to_string() + "/sub/"] {
+        let mut repository = Repository::new("test".to_string(), root);
+        repository.validate(&cache("sub_path")).await.unwrap();
+        assert_eq!(
+            urls(&repository),
+            vec![
+                url.to_string() + "/sub/a.l1t",
+                url.to_string() + "/sub/c.l1t"
+            ]
+        );
+    }
+    assert_eq!(*requests.lock().unwrap(), vec!["/sub/l1t", "/sub/l1t"]);
+}
+
+#[tokio::test]
+async fn sources_cannot_escape_the_repository() {
+    for source in [
+        "../a.l1t",
+        "levels/../../a.l1t",
+        "%2e%2e/a.l1t",
+        "/other/a.l1t",
+        "//example.com/a.l1t",
+        "http://example.com/sub/a.l1t",
+    ] {
+        let (url, _) = serve(vec![route("/sub/l1t", listing(&[source]))]).await;
+        let mut repository = Repository::new("test".to_string(), url + "/sub");
+        let result = repository.validate(&cache("escape")).await;
+        assert!(
+            matches!(&result, Err(RepositoryError::Integrity(e)) if e.contains(&format!("`{source}`"))),
+            "`{source}` should be rejected"
+        );
+    }
+}
+
+#[tokio::test]
+async fn absolute_sources_inside_the_repository_are_allowed() {
+    let (url, _) = serve(vec![]).await;
+    let repository = Repository::new("test".to_string(), url.to_string() + "/sub");
+    let source = url.to_string() + "/sub/a.l1t";
+    assert_eq!(repository.level_url(&source).unwrap().to_string(), source);
+}
+
+#[tokio::test]
+async fn levels_are_downloaded_from_their_source() {
+    let (url, requests) = serve(vec![
+        route("/l1t", listing(&["levels/a.l1t"])),
+        route("/levels/a.l1t", LEVEL.to_string()),
+    ])
+    .await;
+    let cache = cache("levels_are_downloaded");
+    let mut repository = Repository::new("test".to_string(), url + "/");
+    repository.download_listing(&cache).await.unwrap();
+    let info = repository.levels[0].clone();
+    let level = Level::url(info, &repository, &cache).await.unwrap();
+    assert_eq!(level.info.name, "levels/a.l1t");
+    assert_eq!(*requests.lock().unwrap(), vec!["/l1t", "/levels/a.l1t"]);
+}
+
+#[tokio::test]
+async fn version_2_listings_are_paged_and_have_details() {
+    let first = r#"{
+        "version": 2,
+        "levels": [{
+            "source": "a.l1t", "name": "A", "author": "me", "description": "first",
+            "tags": ["easy"], "difficulty": 1, "par": 12, "size": {"width": 20, "height": 10},
+            "hash": "abc", "last_modified": 1700000000
+        }],
+        "next": "l1t?cursor=2"
+    }"#;
+    let second = r#"{"version": 2, "levels": [{"source": "b.l1t", "name": "B", "author": "me", "description": "second"}]}"#;
+    let (url, requests) = serve(vec![route("/l1t", first), route("/l1t?cursor=2", second)]).await;
+    let mut repository = Repository::new("test".to_string(), url);
+    repository.download_listing(&cache("paged")).await.unwrap();
+    assert_eq!(*requests.lock().unwrap(), vec!["/l1t", "/l1t?cursor=2"]);
+    let names: Vec<&str> = repository.levels.iter().map(|l| l.name.as_str()).collect();
+    assert_eq!(names, vec!["A", "B"]);
+    let meta = &repository.levels[0].meta;
+    assert_eq!(meta.tags, vec!["easy"]);
+    assert_eq!(meta.difficulty, Some(1));
+    assert_eq!(meta.par, Some(12));
+    assert_eq!(
+        meta.size,
+        Some(LevelSize {
+            width: 20,
+            height: 10
+        })
+    );
+    assert_eq!(meta.hash.as_deref(), Some("abc"));
+    assert_eq!(meta.last_modified, Some(1700000000));
+    assert!(repository.levels[1].meta.is_empty());
+}
+
+#[tokio::test]
+async fn next_pages_cannot_escape_the_repository() {
+    let listing = r#"{"version": 2, "levels": [], "next": "http://example.com/l1t"}"#;
+    let (url, _) = serve(vec![route("/l1t", listing)]).await;
+    let mut repository = Repository::new("test".to_string(), url);
+    let result = repository.download_listing(&cache("next_escape")).await;
+    assert!(
+        matches!(&result, Err(RepositoryError::Integrity(e)) if e.contains("`http://example.com/l1t`")),
+        "{result:?}"
+    );
+}
+
+#[tokio::test]
+async fn newer_listing_versions_are_rejected() {
+    let listing = r#"{"version": 3, "levels": []}"#;
+    let (url, _) = serve(vec![route("/l1t", listing)]).await;
+    let mut repository = Repository::new("test".to_string(), url);
+    let result = repository.download_listing(&cache("newer_version")).await;
+    assert!(matches!(result, Err(RepositoryError::BadJson(_))));
+}
+
+#[tokio::test]
+async fn searches_are_sent_to_and_filtered_from_the_listing() {
+    let levels = r#"{"levels": [
+        {"source": "a.l1t", "name": "Mirror Maze", "author": "me", "description": "", "tags": ["hard"]},
+        {"source": "b.l1t", "name": "Mirror Lake", "author": "me", "description": "", "tags": ["easy"]},
+        {"source": "c.l1t", "name": "Laser Lake", "author": "me", "description": "", "tags": ["hard"]}
+    ]}"#;
+    // A version 1 server ignores the query, so the results are filtered
+    // by the client too.
+    let (url, requests) = serve(vec![route("/l1t?q=mirror&tag=hard", levels.to_string())]).await;
+    let repository = Repository::new("test".to_string(), url);
+    let query = ListingQuery::parse("mirror tag:hard");
+    assert_eq!(query.text, "mirror");
+    assert_eq!(query.tags, vec!["hard"]);
+    let results = repository.search(&query, &cache("search")).await.unwrap();
+    let names: Vec<&str> = results.levels.iter().map(|l| l.name.as_str()).collect();
+    assert_eq!(names, vec!["Mirror Maze"]);
+    assert_eq!(*requests.lock().unwrap(), vec!["/l1t?q=mirror&tag=hard"]);
+}
+
+fn hashed_listing(hash: &str) -> String {
+    format!(
+        r#"{{"version": 2, "levels": [{{"source": "a.l1t", "name": "A", "author": "me", "description": "", "hash": "{hash}"}}]}}"#
+    )
+}
+
+fn signing_key() -> SigningKey {
+    SigningKey::from_bytes(&[7; 32])
+}
+
+fn sign(content: &str) -> String {
+    hex::encode(signing_key().sign(content.as_bytes()).to_bytes())
+}
+
+fn pinned(url: String) -> Repository {
+    let mut repository = Repository::new("test".to_string(), url);
+    repository.public_key = Some(hex::encode(signing_key().verifying_key().to_bytes()));
+    repository
+}
+
+#[tokio::test]
+async fn levels_must_match_their_hash() {
+    let listing = hashed_listing(&checksum(LEVEL));
+    let (url, _) = serve(vec![route("/l1t", listing), route("/a.l1t", LEVEL)]).await;
+    let cache = cache("hash_matches");
+    let mut repository = Repository::new("test".to_string(), url);
+    repository.download_listing(&cache).await.unwrap();
+    let info = repository.levels[0].clone();
+    let level = Level::url(info, &repository, &cache).await.unwrap();
+    assert_eq!(level.info.meta.hash, Some(checksum(LEVEL)));
+
+    let listing = hashed_listing(&checksum("something else"));
+    let (url, _) = serve(vec![route("/l1t", listing), route("/a.l1t", LEVEL)]).await;
+    let cache = self::cache("hash_differs");
+    let mut repository = Repository::new("test".to_string(), url);
+    repository.download_listing(&cache).await.unwrap();
+    let info = repository.levels[0].clone();
+    let result = Level::url(info, &repository, &cache).await;
+    assert!(matches!(result, Err(RepositoryError::Integrity(_))));
+}
+
+#[tokio::test]
+async fn unhashed_levels_record_the_hash_of_what_was_downloaded() {
+    let (url, _) = serve(vec![
+        route("/l1t", listing(&["a.l1t"])),
+        route("/a.l1t", LEVEL),
+    ])
+    .await;
+    let cache = cache("hash_recorded");
+    let mut repository = Repository::new("test".to_string(), url);
+    repository.download_listing(&cache).await.unwrap();
+    let info = repository.levels[0].clone();
+    let level = Level::url(info, &repository, &cache).await.unwrap();
+    assert_eq!(level.info.meta.hash, Some(checksum(LEVEL)));
+}
+
+#[tokio::test]
+async fn pinned_listings_can_be_signed_in_a_header_or_a_file() {
+    let listing = hashed_listing(&checksum(LEVEL));
+    let signature = sign(&listing);
+    let (url, _) = serve(vec![
+        route("/l1t", listing.to_string()).header(SIGNATURE_HEADER, signature.to_string())
+    ])
+    .await;
+    let mut repository = pinned(url);
+    repository
+        .download_listing(&cache("signed_header"))
+        .await
+        .unwrap();
+    assert_eq!(repository.levels.len(), 1);
+
+    let (url, _) = serve(vec![
+        route("/l1t", listing.to_string()),
+        route("/l1t.sig", signature + "\n"),
+    ])
+    .await;
+    let mut repository = pinned(url);
+    repository
+        .download_listing(&cache("signed_file"))
+        .await
+        .unwrap();
+    assert_eq!(repository.levels.len(), 1);
+}
+
+#[tokio::test]
+async fn pinned_listings_must_be_signed_by_the_key() {
+    let signed = hashed_listing(&checksum(LEVEL));
+    let cases = vec![
+        ("unsigned", route("/l1t", signed.to_string())),
+        (
+            "wrong_content",
+            route("/l1t", signed.to_string()).header(SIGNATURE_HEADER, sign("{}")),
+        ),
+        (
+            "wrong_key",
+            route("/l1t", signed.to_string()).header(
+                SIGNATURE_HEADER,
+                hex::encode(
+                    SigningKey::from_bytes(&[8; 32])
+                        .sign(signed.as_bytes())
+                        .to_bytes(),
+                ),
+            ),
+        ),
+        (
+            "unhashed_levels",
+            route("/l1t", listing(&["a.l1t"])).header(SIGNATURE_HEADER, sign(&listing(&["a.l1t"]))),
+        ),
+    ];
+    for (name, route) in cases {
+        let (url, _) = serve(vec![route]).await;
+        let mut repository = pinned(url);
+        let result = repository.download_listing(&cache(name)).await;
+        assert!(
+            matches!(result, Err(RepositoryError::Integrity(_))),
+            "{name} should be rejected, got {result:?}"
+        );
+    }
+}
+
+#[tokio::test]
+async fn local_repositories_read_their_listing_from_disk() {
+    let dir = temp_dir("local_listing");
+    fs::create_dir_all(dir.join("levels")).unwrap();
+    fs::write(dir.join("l1t"), listing(&["levels/a.l1t"])).unwrap();
+    fs::write(dir.join("levels/a.l1t"), LEVEL).unwrap();
+    let cache = cache("local_listing_cache");
+    let url = format!("file://{}", dir.display());
+    let mut repository = Repository::new("test".to_string(), url);
+    repository.download_listing(&cache).await.unwrap();
+    assert_eq!(
+        urls(&repository),
+        vec![format!("file://{}/levels/a.l1t", dir.display())]
+    );
+    let info = repository.levels[0].clone();
+    let level = Level::url(info, &repository, &cache).await.unwrap();
+    assert_eq!(level.info.name, "levels/a.l1t");
+    assert!(cache.get(&urls(&repository)[0]).is_none());
+}
+
+#[tokio::test]
+async fn directories_without_a_listing_list_their_level_files() {
+    let dir = temp_dir("local_directory");
+    fs::write(dir.join("10.l1t"), format!("Ten\nme\nThe tenth\n{LEVEL}")).unwrap();
+    fs::write(dir.join("2.l1t"), format!("Two\nme\nThe second\n{LEVEL}")).unwrap();
+    fs::write(dir.join("notes.txt"), "not a level").unwrap();
+    fs::create_dir_all(dir.join("drafts.l1t")).unwrap();
+    let cache = cache("local_directory_cache");
+    let mut repository = Repository::new("test".to_string(), dir.display().to_string());
+    repository.download_listing(&cache).await.unwrap();
+    let names: Vec<&str> = repository.levels.iter().map(|l| l.name.as_str()).collect();
+    assert_eq!(names, vec!["Two", "Ten"]);
+    let info = repository.levels[1].clone();
+    assert!(matches!(&info.source, LevelSource::File(path) if path == &dir.join("10.l1t")));
+    let level = Level::url(info, &repository, &cache).await.unwrap();
+    assert_eq!(level.info.name, "Ten");
+    assert_eq!(level.rows as usize, LEVEL.trim().lines().count());
+}
+
+#[tokio::test]
+async fn local_sources_cannot_escape_the_directory() {
+    let dir = temp_dir("local_escape");
+    fs::write(dir.join("l1t"), listing(&["../a.l1t"])).unwrap();
+    let mut repository = Repository::new("test".to_string(), dir.display().to_string());
+    let result = repository
+        .download_listing(&cache("local_escape_cache"))
+        .await;
+    assert!(
+        matches!(&result, Err(RepositoryError::Integrity(e)) if e.contains("`../a.l1t`")),
+        "{result:?}"
+    );
+}
+
+/// Runs `git` in `dir`, returning what it printed.
+fn git(dir: &Path, args: &[&str]) -> String {
+    let output = Command::new("git")
+        .args([
+            "-c",
+            "user.name=l1t",
+            "-c",
+            "user.email=l1t@example.com",
+            "-C",
+        ])
+        .arg(dir)
+        .args(args)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{output:?}");
+    String::from_utf8(output.stdout).unwrap().trim().to_string()
+}
+
+/// A git repository with `levels/a.l1t` tagged as `v1` and then changed
+/// in the commit after it. Returns the directory and both commits.
+fn git_repository(name: &str) -> (PathBuf, String, String) {
+    let dir = temp_dir(name);
+    git(&dir, &["init", "-q"]);
+    fs::create_dir_all(dir.join("levels")).unwrap();
+    fs::write(dir.join("l1t.json"), listing(&["levels/a.l1t"])).unwrap();
+    fs::write(dir.join("levels/a.l1t"), LEVEL).unwrap();
+    git(&dir, &["add", "."]);
+    git(&dir, &["commit", "-q", "-m", "Add a"]);
+    git(&dir, &["tag", "v1"]);
+    let v1 = git(&dir, &["rev-parse", "HEAD"]);
+    fs::write(dir.join("levels/a.l1t"), LEVEL.replacen('S', "R", 1)).unwrap();
+    git(&dir, &["commit", "-q", "-am", "Change a"]);
+    let head = git(&dir, &["rev-parse", "HEAD"]);
+    // Changes that aren't committed aren't listed or played.
+    fs::write(dir.join("levels/a.l1t"), "not a level").unwrap();
+    (dir, v1, head)
+}
+
+fn git_repo(dir: &Path, rev: Option<&str>) -> Repository {
+    let mut repository = Repository::new("test".to_string(), format!("file://{}", dir.display()));
+    repository.kind = RepositoryKind::Git;
+    repository.rev = rev.map(|r| r.to_string());
+    repository
+}
+
+#[tokio::test]
+async fn git_repositories_list_levels_at_their_rev() {
+    let (dir, v1, head) = git_repository("git_rev");
+    let cache = cache("git_rev_cache");
+    for (rev, commit, content) in [
+        (Some("v1"), &v1, LEVEL.to_string()),
+        (None, &head, LEVEL.replacen('S', "R", 1)),
+    ] {
+        let mut repository = git_repo(&dir, rev);
+        repository.download_listing(&cache).await.unwrap();
+        let info = repository.levels[0].clone();
+        match &info.source {
+            LevelSource::Git { url, commit: c } => {
+                assert_eq!(url, &format!("file://{}/levels/a.l1t", dir.display()));
+                assert_eq!(c, commit);
+            }
+            source => panic!("unexpected source {source:?}"),
+        }
+        let level = Level::url(info, &repository, &cache).await.unwrap();
+        assert_eq!(level.info.meta.hash, Some(checksum(&content)));
+    }
+}
+
+#[tokio::test]
+async fn git_repositories_must_be_local_and_have_the_rev() {
+    let (dir, _, _) = git_repository("git_errors");
+    let cache = cache("git_errors_cache");
+    let mut repository = git_repo(&dir, Some("no-such-branch"));
+    let result = repository.download_listing(&cache).await;
+    assert!(matches!(result, Err(RepositoryError::Git(_))), "{result:?}");
+
+    let mut repository = git_repo(&dir, None);
+    repository.url = "http://example.com/levels.git".to_string();
+    let result = repository.download_listing(&cache).await;
+    assert!(
+        matches!(result, Err(RepositoryError::Config(_))),
+        "{result:?}"
+    );
+}
+
+#[tokio::test]
+async fn git_sources_cannot_escape_the_repository() {
+    let dir = temp_dir("git_escape");
+    git(&dir, &["init", "-q"]);
+    fs::write(dir.join("l1t.json"), listing(&["../a.l1t"])).unwrap();
+    git(&dir, &["add", "."]);
+    git(&dir, &["commit", "-q", "-m", "Escape"]);
+    let mut repository = git_repo(&dir, None);
+    let result = repository
+        .download_listing(&cache("git_escape_cache"))
+        .await;
+    assert!(
+        matches!(&result, Err(RepositoryError::Integrity(e)) if e.contains("`../a.l1t`")),
+        "{result:?}"
+    );
+}
+
+#[test]
+fn old_configs_are_converted_and_kept_as_a_backup() {
+    let config = temp_dir("old_config");
+    let old = "main = https://example.com/l1t?key=value&a=b\n\nbroken line\n = nameless\nempty =\n";
+    fs::write(config.join("repositories.l1t_conf"), old).unwrap();
+    let config = config.to_string_lossy().to_string();
+    let repositories = Repository::read_all(&config).unwrap();
+    assert_eq!(repositories.len(), 1);
+    assert_eq!(repositories[0].name, "main");
+    assert_eq!(repositories[0].url, "https://example.com/l1t?key=value&a=b");
+    assert!(repositories[0].enabled);
+
+    assert!(!Path::new(&(config.to_string() + "/repositories.l1t_conf")).exists());
+    assert_eq!(
+        fs::read_to_string(config.to_string() + "/repositories.l1t_conf.bak").unwrap(),
+        old
+    );
+    let toml = fs::read_to_string(config.to_string() + "/repositories.toml").unwrap();
+    assert!(
+        toml.contains("url = \"https://example.com/l1t?key=value&a=b\""),
+        "{toml}"
+    );
+    // Once converted, the new file is read as it is.
+    let repositories = Repository::read_all(&config).unwrap();
+    assert_eq!(repositories[0].url, "https://example.com/l1t?key=value&a=b");
+}
+
+#[tokio::test]
+async fn forgotten_levels_are_listed_again() {
+    let (old_url, _) = serve(vec![route("/l1t", listing(&["a.l1t"]))]).await;
+    let (new_url, requests) = serve(vec![route("/l1t", listing(&["b.l1t", "c.l1t"]))]).await;
+    let cache = cache("forget_levels");
+    let mut repository = Repository::new("test".to_string(), old_url);
+    repository.download_listing(&cache).await.unwrap();
+    repository.url = new_url.to_string();
+    repository.download_listing(&cache).await.unwrap();
+    assert!(requests.lock().unwrap().is_empty());
+
+    repository.forget_levels();
+    assert!(repository.levels.is_empty());
+    repository.download_listing(&cache).await.unwrap();
+    assert_eq!(*requests.lock().unwrap(), vec!["/l1t"]);
+    assert_eq!(
+        urls(&repository),
+        vec![new_url.to_string() + "/b.l1t", new_url + "/c.l1t"]
+    );
+}
+
+/// Puts an old copy of `url` with the ETag `etag` in `cache`.
+fn cached(cache: &Cache, url: &str, etag: &str, content: &str) {
+    let entry = CacheEntry::new(url, Some(etag.to_string()), None, content.to_string());
+    cache.put(&entry).unwrap();
+}
+
+#[tokio::test]
+async fn cached_copies_are_revalidated_with_their_etag() {
+    let (url, requests) = serve(vec![route("/a.l1t", "new").header("ETag", "\"v2\"")]).await;
+    let cache = cache("revalidate");
+    let mut repository = Repository::new("test".to_string(), url.to_string());
+    let level = url + "/a.l1t";
+    cached(&cache, &level, "\"v2\"", "old");
+    // Copies checked less than `cache_ttl` seconds ago aren't checked again.
+    let download = repository.download(&level, &cache).await.unwrap();
+    assert_eq!(download.content, "old");
+    assert!(requests.lock().unwrap().is_empty());
+
+    repository.cache_ttl = 0;
+    let download = repository.download(&level, &cache).await.unwrap();
+    assert_eq!(download.content, "old");
+    assert!(!download.stale);
+
+    cached(&cache, &level, "\"v1\"", "older");
+    let download = repository.download(&level, &cache).await.unwrap();
+    assert_eq!(download.content, "new");
+    assert_eq!(cache.get(&level).unwrap().etag.as_deref(), Some("\"v2\""));
+    assert_eq!(*requests.lock().unwrap(), vec!["/a.l1t", "/a.l1t"]);
+}
+
+#[tokio::test]
+async fn cached_copies_stand_in_while_the_repository_is_down() {
+    let (url, _) = serve(vec![route("/broken.l1t", "").status(503)]).await;
+    let cache = cache("stand_in");
+    for level in [closed_url().await + "/a.l1t", url + "/broken.l1t"] {
+        let mut repository = Repository::new("test".to_string(), level.to_string());
+        repository.cache_ttl = 0;
+        cached(&cache, &level, "\"v1\"", "old");
+        let download = repository.download(&level, &cache).await.unwrap();
+        assert_eq!(download.content, "old");
+        assert!(download.stale, "{level}");
+    }
+}
+
+#[tokio::test]
+async fn cached_copies_do_not_hide_other_errors() {
+    let (url, _) = serve(vec![]).await;
+    let cache = cache("no_stand_in");
+    let mut repository = Repository::new("test".to_string(), url.to_string());
+    repository.cache_ttl = 0;
+    let level = url + "/gone.l1t";
+    cached(&cache, &level, "\"v1\"", "old");
+    let result = repository.download(&level, &cache).await;
+    assert!(
+        matches!(&result, Err(RepositoryError::Status { status, .. }) if status.as_u16() == 404),
+        "{:?}",
+        result.map(|d| d.content)
+    );
+}
+
+#[tokio::test]
+async fn every_level_is_downloaded_for_offline_play() {
+    let (url, requests) = serve(vec![
+        route("/l1t", listing(&["a.l1t", "b.l1t", "missing.l1t"])),
+        route("/a.l1t", LEVEL),
+        route("/b.l1t", pull_level()),
+    ])
+    .await;
+    let cache = cache("download_all");
+    let mut repository = Repository::new("test".to_string(), url.to_string());
+    assert_eq!(repository.download_all(&cache).await.unwrap(), 2);
+    assert_eq!(repository.levels.len(), 3);
+    assert_eq!(
+        cache.get(&(url.to_string() + "/a.l1t")).unwrap().content,
+        LEVEL
+    );
+    assert_eq!(
+        cache.get(&(url.to_string() + "/b.l1t")).unwrap().content,
+        pull_level()
+    );
+    assert!(cache.get(&(url + "/missing.l1t")).is_none());
+    assert_eq!(
+        *requests.lock().unwrap(),
+        vec!["/l1t", "/a.l1t", "/b.l1t", "/missing.l1t"]
+    );
+}
+
+#[tokio::test]
+async fn server_errors_are_retried() {
+    let (url, requests) = serve(vec![route("/a.l1t", LEVEL).failing(1)]).await;
+    let repository = Repository::new("test".to_string(), url.to_string());
+    let download = repository
+        .download(&(url + "/a.l1t"), &cache("retry"))
+        .await
+        .unwrap();
+    assert_eq!(download.content, LEVEL);
+    assert_eq!(*requests.lock().unwrap(), vec!["/a.l1t", "/a.l1t"]);
+
+    let (url, requests) = serve(vec![route("/a.l1t", LEVEL).failing(5)]).await;
+    let result = repository
+        .download(&(url + "/a.l1t"), &cache("retry_fails"))
+        .await;
+    assert!(
+        matches!(&result, Err(RepositoryError::Status { status, .. }) if status.as_u16() == 500),
+        "{:?}",
+        result.map(|d| d.content)
+    );
+    assert_eq!(requests.lock().unwrap().len(), 3);
+}
+
+#[tokio::test]
+async fn client_errors_are_not_retried() {
+    let (url, requests) = serve(vec![]).await;
+    let repository = Repository::new("test".to_string(), url.to_string());
+    let result = repository
+        .download(&(url + "/missing.l1t"), &cache("no_retry"))
+        .await;
+    assert!(
+        matches!(&result, Err(RepositoryError::Status { status, .. }) if status.as_u16() == 404),
+        "{:?}",
+        result.map(|d| d.content)
+    );
+    assert_eq!(*requests.lock().unwrap(), vec!["/missing.l1t"]);
+}
+
+#[tokio::test]
+async fn unreachable_repositories_are_network_errors() {
+    let url = closed_url().await;
+    let repository = Repository::new("test".to_string(), url.to_string());
+    let result = repository
+        .download(&(url + "/a.l1t"), &cache("unreachable"))
+        .await;
+    assert!(
+        matches!(result, Err(RepositoryError::Network(_))),
+        "{:?}",
+        result.map(|d| d.content)
+    );
+}
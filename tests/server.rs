@@ -0,0 +1,235 @@
+use l1t::{
+    level::{checksum, Level},
+    moves::Move,
+    repository::{ListingQuery, Repository, RepositoryError, RepositoryResponse},
+    server::{LevelServer, RESULTS_FILE},
+};
+use std::{fs, path::Path, time::Duration};
+
+mod common;
+use common::{cache, pull_level, temp_dir, LEVEL};
+
+/// Starts a server for `dir` on any free port and returns its URL.
+fn serve(dir: &Path) -> String {
+    let server = LevelServer::bind(dir, "127.0.0.1:0").unwrap();
+    let url = format!("http://{}", server.local_addr().unwrap());
+    tokio::spawn(server.run());
+    url
+}
+
+/// Starts a server for `dir` that accepts uploads and returns a
+/// repository for it.
+fn serve_uploads(dir: &Path) -> Repository {
+    let server = LevelServer::bind(dir, "127.0.0.1:0")
+        .unwrap()
+        .accept_uploads();
+    let url = format!("http://{}", server.local_addr().unwrap());
+    tokio::spawn(server.run());
+    Repository::new("lan".to_string(), url)
+}
+
+fn rejected(result: Result<impl std::fmt::Debug, RepositoryError>) -> String {
+    match result {
+        Err(RepositoryError::Rejected(e)) => e,
+        other => panic!("expected the repository to turn it down, got {other:?}"),
+    }
+}
+
+fn write_level(dir: &Path, file: &str, name: &str) {
+    fs::write(dir.join(file), format!("{name}\nme\nAbout {name}\n{LEVEL}")).unwrap();
+}
+
+#[tokio::test]
+async fn served_levels_can_be_played() {
+    let dir = temp_dir("played");
+    write_level(&dir, "2.l1t", "Two");
+    write_level(&dir, "my level.l1t", "Mine");
+    fs::write(dir.join("broken.l1t"), "Broken\nme\nNot a level\n###\n").unwrap();
+    let mut repository = Repository::new("lan".to_string(), serve(&dir));
+    let cache = cache("played_cache");
+    repository.download_listing(&cache).await.unwrap();
+    let names: Vec<&str> = repository.levels.iter().map(|l| l.name.as_str()).collect();
+    assert_eq!(names, vec!["Two", "Mine"]);
+    for info in &repository.levels {
+        assert_eq!(info.meta.hash, Some(checksum(LEVEL)));
+        let level = Level::url(info.clone(), &repository, &cache).await.unwrap();
+        assert_eq!(level.info.author, "me");
+    }
+}
+
+#[tokio::test]
+async fn levels_with_url_characters_in_their_names_can_be_played() {
+    let dir = temp_dir("url_names");
+    write_level(&dir, "a #1?%.l1t", "Odd");
+    let mut repository = Repository::new("lan".to_string(), serve(&dir));
+    let cache = cache("url_names_cache");
+    repository.download_listing(&cache).await.unwrap();
+    let info = repository.levels[0].clone();
+    assert_eq!(
+        info.source.url().unwrap(),
+        format!("{}/levels/a%20%231%3F%25.l1t", repository.url)
+    );
+    let level = Level::url(info, &repository, &cache).await.unwrap();
+    assert_eq!(level.info.name, "Odd");
+}
+
+#[tokio::test]
+async fn changes_are_served_straight_away() {
+    let dir = temp_dir("changes");
+    write_level(&dir, "a.l1t", "A");
+    let mut repository = Repository::new("lan".to_string(), serve(&dir));
+    repository.cache_ttl = 0;
+    let cache = cache("changes_cache");
+    repository.download_listing(&cache).await.unwrap();
+    assert_eq!(repository.levels.len(), 1);
+    write_level(&dir, "b.l1t", "B");
+    repository.download_listing(&cache).await.unwrap();
+    let names: Vec<&str> = repository.levels.iter().map(|l| l.name.as_str()).collect();
+    assert_eq!(names, vec!["A", "B"]);
+}
+
+#[tokio::test]
+async fn listings_are_searched_on_the_server() {
+    let dir = temp_dir("search");
+    write_level(&dir, "a.l1t", "Mirror Maze");
+    write_level(&dir, "b.l1t", "Zapper Field");
+    let url = serve(&dir);
+    let response: RepositoryResponse = reqwest::get(format!("{url}/l1t?q=maze"))
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let names: Vec<&str> = response.levels.iter().map(|l| l.name.as_str()).collect();
+    assert_eq!(names, vec!["Mirror Maze"]);
+
+    let repository = Repository::new("lan".to_string(), url);
+    let cache = cache("search_cache");
+    let results = repository
+        .search(&ListingQuery::parse("zapper"), &cache)
+        .await
+        .unwrap();
+    assert_eq!(results.levels.len(), 1);
+    assert_eq!(results.levels[0].name, "Zapper Field");
+}
+
+#[tokio::test]
+async fn only_levels_in_the_directory_are_served() {
+    let root = temp_dir("escape");
+    let dir = root.join("levels");
+    fs::create_dir_all(&dir).unwrap();
+    write_level(&root, "secret.l1t", "Secret");
+    write_level(&dir, "a.l1t", "A");
+    fs::write(dir.join("notes.txt"), "not a level").unwrap();
+    let url = serve(&dir);
+    let status = |path: &'static str| {
+        let url = url.clone();
+        async move { reqwest::get(format!("{url}{path}")).await.unwrap().status() }
+    };
+    assert!(status("/levels/a.l1t").await.is_success());
+    for path in [
+        "/levels/..%2Fsecret.l1t",
+        "/levels/%2E%2E%2Fsecret.l1t",
+        "/levels/notes.txt",
+        "/levels/missing.l1t",
+        "/a.l1t",
+    ] {
+        assert_eq!(status(path).await, reqwest::StatusCode::NOT_FOUND, "{path}");
+    }
+}
+
+#[test]
+fn servers_need_a_directory() {
+    let dir = temp_dir("not_a_dir");
+    assert!(LevelServer::bind(&dir.join("missing"), "127.0.0.1:0").is_err());
+}
+
+#[tokio::test]
+async fn published_levels_are_checked_and_listed() {
+    let dir = temp_dir("publish");
+    let mut repository = serve_uploads(&dir);
+    let solution = Move::parse("Dp").unwrap();
+    let info = repository
+        .upload_level(&pull_level(), &solution)
+        .await
+        .unwrap();
+    assert_eq!(info.source, "levels/pull-it-out.l1t");
+    assert_eq!(
+        fs::read_to_string(dir.join("pull-it-out.l1t")).unwrap(),
+        pull_level()
+    );
+    let cache = cache("publish_cache");
+    repository.download_listing(&cache).await.unwrap();
+    assert_eq!(repository.levels[0].name, "Pull It Out!");
+
+    let again = rejected(repository.upload_level(&pull_level(), &solution).await);
+    assert!(again.contains("already"), "{again}");
+    let renamed = pull_level().replacen("Pull It Out!", "Stuck", 1);
+    let losing = Move::parse("D").unwrap();
+    let unsolved = rejected(repository.upload_level(&renamed, &losing).await);
+    assert!(unsolved.contains("doesn't win"), "{unsolved}");
+    rejected(
+        repository
+            .upload_level("Broken\nme\nNope\n###", &solution)
+            .await,
+    );
+    assert!(!dir.join("stuck.l1t").exists());
+}
+
+#[tokio::test]
+async fn repositories_only_accept_uploads_when_asked() {
+    let dir = temp_dir("read_only");
+    let solution = Move::parse("Dp").unwrap();
+    for repository in [
+        Repository::new("lan".to_string(), serve(&dir)),
+        Repository::new("local".to_string(), format!("file://{}", dir.display())),
+    ] {
+        let result = repository.upload_level(&pull_level(), &solution).await;
+        assert!(
+            matches!(result, Err(RepositoryError::ReadOnly(_))),
+            "{result:?}"
+        );
+    }
+    assert!(!dir.join("pull-it-out.l1t").exists());
+}
+
+#[tokio::test]
+async fn results_are_reported_for_served_levels() {
+    let dir = temp_dir("results");
+    write_level(&dir, "a.l1t", "A");
+    let mut repository = serve_uploads(&dir);
+    let cache = cache("results_cache");
+    repository.download_listing(&cache).await.unwrap();
+    let mut info = repository.levels[0].clone();
+    repository
+        .report_result(&info, 12, Duration::from_secs(30))
+        .await
+        .unwrap();
+    let results = fs::read_to_string(dir.join(RESULTS_FILE)).unwrap();
+    assert_eq!(
+        results,
+        format!(
+            "{{\"level\":\"levels/a.l1t\",\"hash\":\"{}\",\"moves\":12,\"time\":30}}\n",
+            checksum(LEVEL)
+        )
+    );
+
+    info.meta.hash = Some(checksum(LEVEL).to_uppercase());
+    repository
+        .report_result(&info, 9, Duration::from_secs(20))
+        .await
+        .unwrap();
+    assert_eq!(
+        fs::read_to_string(dir.join(RESULTS_FILE))
+            .unwrap()
+            .lines()
+            .count(),
+        2
+    );
+
+    info.meta.hash = Some(checksum("something else"));
+    let changed = rejected(repository.report_result(&info, 12, Duration::ZERO).await);
+    assert!(changed.contains("changed"), "{changed}");
+    fs::remove_file(dir.join("a.l1t")).unwrap();
+    rejected(repository.report_result(&info, 12, Duration::ZERO).await);
+}
@@ -0,0 +1,133 @@
+use crossterm::style::Color;
+use l1t::theme::{ColorSupport, Theme};
+use std::fs;
+
+mod common;
+use common::temp_dir;
+
+#[test]
+fn custom_themes_change_colors_on_top_of_their_base() {
+    let theme = Theme::parse(
+        "base = \"light\"\nstatue_lit = \"#ffd700\"\nlaser_on = \"red\"\nwall = \"240\"\nbeam = \"Dark_Grey\"",
+    )
+    .unwrap();
+    assert_eq!(
+        theme.statue_lit,
+        Color::Rgb {
+            r: 255,
+            g: 215,
+            b: 0
+        }
+    );
+    assert_eq!(theme.laser_on, Color::Red);
+    assert_eq!(theme.wall, Color::AnsiValue(240));
+    assert_eq!(theme.beam, Color::DarkGrey);
+    assert_eq!(theme.player, Theme::light().player);
+
+    let theme = Theme::parse("mirror = \"blue\"").unwrap();
+    assert_eq!(theme.mirror, Color::Blue);
+    assert_eq!(theme.text, Theme::dark().text);
+}
+
+#[test]
+fn bad_custom_themes_are_errors() {
+    for (content, error) in [
+        ("base = \"sepia\"", "unknown base theme `sepia`"),
+        ("lasers = \"red\"", "unknown role `lasers`"),
+        ("wall = \"#12345\"", "invalid color `#12345` for wall"),
+        ("wall = \"#gg0000\"", "invalid color `#gg0000` for wall"),
+        ("wall = \"256\"", "invalid color `256` for wall"),
+        ("wall = \"reset\"", "invalid color `reset` for wall"),
+        (
+            "wall = \"bright pink\"",
+            "invalid color `bright pink` for wall",
+        ),
+        ("wall = 12", ""),
+        ("wall = ", ""),
+    ] {
+        let e = Theme::parse(content).unwrap_err();
+        assert!(e.contains(error), "{content}: {e}");
+    }
+}
+
+#[test]
+fn custom_themes_are_loaded_from_the_config_directory() {
+    let config = temp_dir("themes");
+    fs::create_dir_all(config.join("themes")).unwrap();
+    fs::write(config.join("themes/mine.toml"), "zapper = \"green\"").unwrap();
+    fs::write(config.join("themes/dark.toml"), "zapper = \"green\"").unwrap();
+    let config = config.to_string_lossy();
+    assert_eq!(
+        Theme::names(&config),
+        vec!["dark", "light", "high-contrast", "deuteranopia", "mine"]
+    );
+    assert_eq!(Theme::load("mine", &config).unwrap().zapper, Color::Green);
+    assert_eq!(Theme::load("dark", &config).unwrap(), Theme::dark());
+    let e = Theme::load("missing", &config).unwrap_err();
+    assert!(e.contains("unknown theme `missing`"), "{e}");
+}
+
+/// What `color` becomes on a terminal with `support`.
+fn adapted(color: Color, support: ColorSupport) -> Color {
+    let theme = Theme {
+        wall: color,
+        ..Theme::dark()
+    };
+    theme.adapted(support).wall
+}
+
+const fn rgb(r: u8, g: u8, b: u8) -> Color {
+    Color::Rgb { r, g, b }
+}
+
+#[test]
+fn colors_are_matched_to_the_16_standard_colors() {
+    for (color, basic) in [
+        (rgb(0, 0, 0), Color::Black),
+        (rgb(255, 255, 255), Color::White),
+        (rgb(128, 128, 128), Color::DarkGrey),
+        (rgb(200, 0, 0), Color::DarkRed),
+        (rgb(250, 10, 10), Color::Red),
+        // Dim colors stay colorful instead of turning black or grey.
+        (rgb(100, 0, 0), Color::DarkRed),
+        (rgb(20, 20, 150), Color::DarkBlue),
+        (rgb(255, 215, 0), Color::Yellow),
+        (Color::AnsiValue(196), Color::Red),
+        (Color::AnsiValue(9), Color::Red),
+        (Color::AnsiValue(244), Color::DarkGrey),
+        (Color::Magenta, Color::Magenta),
+    ] {
+        assert_eq!(adapted(color, ColorSupport::Basic), basic, "{color:?}");
+    }
+}
+
+#[test]
+fn colors_are_matched_to_the_256_color_palette() {
+    for (color, ansi) in [
+        (rgb(0, 0, 0), 16),
+        (rgb(255, 0, 0), 196),
+        (rgb(255, 215, 0), 220),
+        (rgb(95, 135, 175), 67),
+        // Greys are closer to the grey ramp than to the color cube.
+        (rgb(128, 128, 128), 244),
+        (rgb(18, 18, 18), 233),
+        (Color::AnsiValue(100), 100),
+    ] {
+        assert_eq!(
+            adapted(color, ColorSupport::Ansi256),
+            Color::AnsiValue(ansi),
+            "{color:?}"
+        );
+    }
+    assert_eq!(adapted(Color::Cyan, ColorSupport::Ansi256), Color::Cyan);
+}
+
+#[test]
+fn colors_are_kept_or_dropped_by_what_the_terminal_supports() {
+    let theme = Theme::parse("statue_lit = \"#ffd700\"").unwrap();
+    assert_eq!(theme.clone().adapted(ColorSupport::TrueColor), theme);
+    let monochrome = theme.adapted(ColorSupport::None);
+    assert!(monochrome.monochrome);
+    assert_eq!(monochrome.statue_lit, Color::Reset);
+    assert_eq!(monochrome.text, Color::Reset);
+}
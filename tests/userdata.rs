@@ -0,0 +1,357 @@
+use l1t::{
+    direction::Direction,
+    level::{Level, LevelSource},
+    moves::Move,
+    paths::Paths,
+    settings::Settings,
+    userdata::{CompletedRepoLevel, SerializedUserData, Stats, UserData},
+};
+use std::{collections::BTreeMap, fs, path::Path};
+
+mod common;
+use common::temp_dir;
+
+/// Reads `profile` from the data directory `root`.
+fn user_data(root: &Path, profile: &str) -> UserData {
+    let paths = Paths::new(Some(root.to_string_lossy().to_string()));
+    UserData::read(paths, Some(profile.to_string())).unwrap()
+}
+
+fn mirror_level() -> Level {
+    let content = "Mirror\nme\nA mirror to turn\nIIIIII\nI/X SI\nIIIIII";
+    Level::parse(content, LevelSource::Core(0)).unwrap()
+}
+
+/// `level` after a move, so that it's saved.
+fn moved(mut level: Level) -> Level {
+    level.apply(Move::Toggle(Direction::LEFT));
+    level
+}
+
+#[test]
+fn levels_with_only_toggles_are_saved() {
+    let user_data = user_data(&temp_dir("toggles"), "p");
+    user_data.save_level(mirror_level()).unwrap();
+    assert!(user_data.saved_level().is_none());
+
+    let mut level = mirror_level();
+    level.apply(Move::Toggle(Direction::LEFT));
+    assert_eq!(level.moves, 0);
+    user_data.save_level(level).unwrap();
+    let saved = user_data.saved_level().unwrap();
+    assert_eq!(saved.history, vec![Move::Toggle(Direction::LEFT)]);
+}
+
+/// Writes `content` as the `data.json` of `profile` in `root`.
+fn write_data(root: &Path, profile: &str, file: &str, content: &str) {
+    let dir = root.join("profiles").join(profile);
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join(file), content).unwrap();
+}
+
+const COMPLETED: &str = r#"[{"url": "http://a/l1t/a.l1t", "name": "A", "author": "me", "description": "", "completed_at": 5}]"#;
+
+#[test]
+fn every_old_version_is_migrated() {
+    let versions = [
+        format!(
+            r#"{{"file": "/old/data.json", "completed_core_levels": [0, 1], "completed_levels": {COMPLETED}}}"#
+        ),
+        format!(
+            r#"{{"version": 1, "completed_core_levels": [0, 1], "completed_levels": {COMPLETED}}}"#
+        ),
+        format!(
+            r#"{{"version": 2, "completed_core_levels": [0, 1], "completed_levels": {COMPLETED},
+            "stats": {{"levels_won": 2, "levels_lost": 0, "total_moves": 9, "best_moves": {{}}}}}}"#
+        ),
+    ];
+    for (version, content) in versions.iter().enumerate() {
+        let root = temp_dir(&format!("migrate_{version}"));
+        write_data(&root, "p", "data.json", content);
+        let user_data = user_data(&root, "p");
+        assert!(user_data.warnings.is_empty(), "version {version}");
+        assert_eq!(user_data.completed_core_levels, vec![0, 1]);
+        assert_eq!(user_data.completed_levels[0].name, "A");
+        assert!(user_data.completed_pack_levels.is_empty());
+        let moves = if version == 2 { 9 } else { 0 };
+        assert_eq!(user_data.stats.total_moves, moves);
+    }
+}
+
+#[test]
+fn progress_from_before_profiles_moves_to_the_default_profile() {
+    let root = temp_dir("before_profiles");
+    fs::write(
+        root.join("data.json"),
+        r#"{"version": 3, "completed_core_levels": [2], "completed_levels": [],
+        "completed_pack_levels": {}, "stats": {"levels_won": 1, "levels_lost": 0, "total_moves": 3, "best_moves": {}}}"#,
+    )
+    .unwrap();
+    let user_data = user_data(&root, UserData::DEFAULT_PROFILE);
+    assert_eq!(user_data.completed_core_levels, vec![2]);
+    assert!(!root.join("data.json").exists());
+}
+
+#[test]
+fn corrupt_progress_is_restored_from_the_backup() {
+    let root = temp_dir("corrupt_backup");
+    write_data(&root, "p", "data.json", "{not json");
+    write_data(
+        &root,
+        "p",
+        "data.json.bak",
+        r#"{"version": 1, "completed_core_levels": [3], "completed_levels": []}"#,
+    );
+    let user_data = user_data(&root, "p");
+    assert_eq!(user_data.completed_core_levels, vec![3]);
+    assert_eq!(user_data.warnings.len(), 1);
+    let corrupt = fs::read_to_string(root.join("profiles/p/data.json.corrupt")).unwrap();
+    assert_eq!(corrupt, "{not json");
+}
+
+#[test]
+fn corrupt_progress_without_a_backup_starts_over() {
+    let root = temp_dir("corrupt_reset");
+    write_data(&root, "p", "data.json", r#"{"version": "three"}"#);
+    let user_data = user_data(&root, "p");
+    assert!(user_data.completed_core_levels.is_empty());
+    assert_eq!(user_data.warnings.len(), 1);
+    assert!(root.join("profiles/p/data.json.corrupt").exists());
+}
+
+#[test]
+fn progress_from_newer_versions_is_left_alone() {
+    let root = temp_dir("newer");
+    let newer = r#"{"version": 99, "something_new": true}"#;
+    write_data(&root, "p", "data.json", newer);
+    let paths = Paths::new(Some(root.to_string_lossy().to_string()));
+    assert!(UserData::read(paths, Some("p".to_string())).is_err());
+    let content = fs::read_to_string(root.join("profiles/p/data.json")).unwrap();
+    assert_eq!(content, newer);
+}
+
+fn completed(url: &str, name: &str, completed_at: u64, hash: Option<&str>) -> CompletedRepoLevel {
+    CompletedRepoLevel {
+        url: url.to_string(),
+        name: name.to_string(),
+        author: "me".to_string(),
+        description: String::new(),
+        completed_at,
+        hash: hash.map(str::to_string),
+        commit: None,
+    }
+}
+
+/// Writes progress as `l1t progress export` would.
+fn export(file: &Path, completed_levels: Vec<CompletedRepoLevel>, stats: Stats) {
+    let data = SerializedUserData {
+        version: SerializedUserData::VERSION,
+        completed_core_levels: vec![1, 4],
+        completed_levels,
+        completed_pack_levels: BTreeMap::from([("pack".to_string(), vec![0, 2])]),
+        stats,
+    };
+    fs::write(file, serde_json::to_string(&data).unwrap()).unwrap();
+}
+
+/// A profile in `root` with some progress of its own.
+fn played(root: &Path) -> UserData {
+    let here = root.join("here.json");
+    export(
+        &here,
+        vec![
+            completed("http://a/l1t/x.l1t", "X", 50, None),
+            completed("http://a/l1t/y.l1t", "Y", 10, Some("ab")),
+        ],
+        Stats {
+            levels_won: 5,
+            levels_lost: 1,
+            total_moves: 100,
+            best_moves: BTreeMap::from([("x".to_string(), 7), ("y".to_string(), 3)]),
+        },
+    );
+    let mut user_data = user_data(root, "p");
+    user_data.import_progress(&here).unwrap();
+    user_data
+}
+
+#[test]
+fn imported_progress_is_merged() {
+    let root = temp_dir("import");
+    let mut user_data = played(&root);
+    let there = root.join("there.json");
+    export(
+        &there,
+        vec![
+            completed("http://a/l1t/x.l1t", "X", 20, None),
+            completed("http://b/l1t/y.l1t", "Y", 30, Some("AB")),
+            completed("http://c/l1t/z.l1t", "Z", 40, None),
+        ],
+        Stats {
+            levels_won: 3,
+            levels_lost: 4,
+            total_moves: 80,
+            best_moves: BTreeMap::from([("x".to_string(), 9), ("y".to_string(), 2)]),
+        },
+    );
+    assert_eq!(user_data.import_progress(&there).unwrap(), 1);
+
+    let levels: Vec<(&str, u64)> = user_data
+        .completed_levels
+        .iter()
+        .map(|l| (l.name.as_str(), l.completed_at))
+        .collect();
+    assert_eq!(levels, vec![("X", 20), ("Y", 10), ("Z", 40)]);
+    assert_eq!(user_data.completed_core_levels, vec![1, 4]);
+    assert_eq!(user_data.completed_pack_levels["pack"], vec![0, 2]);
+    assert_eq!(user_data.stats.levels_won, 5);
+    assert_eq!(user_data.stats.levels_lost, 4);
+    assert_eq!(user_data.stats.total_moves, 100);
+    assert_eq!(user_data.stats.best_moves["x"], 7);
+    assert_eq!(user_data.stats.best_moves["y"], 2);
+
+    // Importing the same progress again changes nothing.
+    assert_eq!(user_data.import_progress(&there).unwrap(), 0);
+    assert_eq!(user_data.completed_levels.len(), 3);
+    assert_eq!(user_data.stats.levels_won, 5);
+}
+
+#[test]
+fn levels_with_the_same_name_in_other_repositories_are_different_levels() {
+    let root = temp_dir("same_name");
+    let mut user_data = played(&root);
+    let there = root.join("there.json");
+    export(
+        &there,
+        vec![
+            completed("http://b/l1t/x.l1t", "X", 20, None),
+            completed("http://b/l1t/y.l1t", "Y", 5, Some("cd")),
+        ],
+        Stats::default(),
+    );
+    assert_eq!(user_data.import_progress(&there).unwrap(), 2);
+    let urls: Vec<&str> = user_data
+        .completed_levels
+        .iter()
+        .map(|l| l.url.as_str())
+        .collect();
+    assert_eq!(
+        urls,
+        vec![
+            "http://a/l1t/x.l1t",
+            "http://a/l1t/y.l1t",
+            "http://b/l1t/x.l1t",
+            "http://b/l1t/y.l1t"
+        ]
+    );
+    assert_eq!(user_data.completed_levels[0].completed_at, 50);
+}
+
+#[test]
+fn imports_that_are_not_progress_change_nothing() {
+    let root = temp_dir("bad_import");
+    let mut user_data = played(&root);
+    let file = root.join("notes.txt");
+    fs::write(&file, "not progress").unwrap();
+    assert!(user_data.import_progress(&file).is_err());
+    let user_data = self::user_data(&root, "p");
+    assert_eq!(user_data.completed_levels.len(), 2);
+    assert_eq!(user_data.stats.levels_won, 5);
+}
+
+#[test]
+fn each_profile_keeps_its_own_settings() {
+    let root = temp_dir("settings");
+    fs::write(root.join("settings.toml"), "animation_delay = 100\n").unwrap();
+    let mut first = user_data(&root, "first");
+    assert_eq!(first.settings.animation_delay, 100);
+    first
+        .save_settings(Settings {
+            ascii_only: true,
+            ..first.settings.clone()
+        })
+        .unwrap();
+
+    let second = user_data(&root, "second");
+    assert_eq!(second.settings.animation_delay, 100);
+    assert!(!second.settings.ascii_only);
+    let paths = Paths::new(Some(root.to_string_lossy().to_string()));
+    assert!(!UserData::read_settings(&paths, None).unwrap().ascii_only);
+    assert!(
+        UserData::read_settings(&paths, Some("first".to_string()))
+            .unwrap()
+            .ascii_only
+    );
+    assert!(user_data(&root, "first").settings.ascii_only);
+    let shared = fs::read_to_string(root.join("settings.toml")).unwrap();
+    assert_eq!(shared, "animation_delay = 100\n");
+}
+
+#[test]
+fn bad_profile_names_are_rejected() {
+    for name in ["", "  ", "..", ".", "a/b", "a\\b", "~me", &"a".repeat(33)] {
+        assert!(UserData::validate_profile_name(name).is_err(), "{name:?}");
+    }
+    for name in ["default", "Player 2", "kid_1", "a-b", &"a".repeat(32)] {
+        assert!(UserData::validate_profile_name(name).is_ok(), "{name:?}");
+    }
+    let root = temp_dir("bad_names");
+    let paths = Paths::new(Some(root.to_string_lossy().to_string()));
+    assert!(UserData::read(paths, Some("../escape".to_string())).is_err());
+    assert!(!root.join("escape").exists());
+}
+
+#[test]
+fn the_last_profile_played_is_remembered() {
+    let root = temp_dir("last_profile");
+    assert_eq!(user_data(&root, "second").profile, "second".to_string());
+    let paths = Paths::new(Some(root.to_string_lossy().to_string()));
+    let mut user_data = UserData::read(paths.clone(), None).unwrap();
+    assert_eq!(user_data.profile, "second");
+    user_data.switch_profile("third").unwrap();
+    assert_eq!(user_data.profile, "third");
+    assert_eq!(UserData::read(paths, None).unwrap().profile, "third");
+    assert_eq!(user_data.profiles(), vec!["second", "third"]);
+}
+
+#[test]
+fn copied_profiles_are_independent() {
+    let root = temp_dir("copy");
+    let mut first = user_data(&root, "first");
+    first.complete(mirror_level().info).unwrap();
+    first.save_level(moved(mirror_level())).unwrap();
+    first.copy_profile("first", "second").unwrap();
+    assert!(first.copy_profile("first", "second").is_err());
+    assert!(first.copy_profile("first", "a/b").is_err());
+
+    let mut second = user_data(&root, "second");
+    assert_eq!(second.completed_core_levels, vec![0]);
+    assert!(second.saved_level().is_some());
+    second.reset_profile("second").unwrap();
+    assert!(second.completed_core_levels.is_empty());
+    let first = user_data(&root, "first");
+    assert_eq!(first.completed_core_levels, vec![0]);
+    assert!(first.saved_level().is_some());
+}
+
+#[test]
+fn resetting_a_profile_keeps_its_settings() {
+    let root = temp_dir("reset");
+    let mut user_data = user_data(&root, "p");
+    user_data.complete(mirror_level().info).unwrap();
+    user_data.save_level(moved(mirror_level())).unwrap();
+    user_data
+        .save_settings(Settings {
+            ascii_only: true,
+            ..user_data.settings.clone()
+        })
+        .unwrap();
+    user_data.reset_profile("p").unwrap();
+    assert!(user_data.completed_core_levels.is_empty());
+    assert_eq!(user_data.stats.levels_won, 0);
+    assert!(user_data.saved_level().is_none());
+    assert!(user_data.settings.ascii_only);
+    let user_data = self::user_data(&root, "p");
+    assert!(user_data.completed_core_levels.is_empty());
+    assert!(user_data.settings.ascii_only);
+}